@@ -0,0 +1,123 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+
+// Cycle detection over the sequence x0, f(x0), f(f(x0)), ... of a function with a finite
+// number of states. Any such sequence eventually repeats: after a tail of |tail_length|
+// states, it loops over a cycle of |period| states forever.
+//
+// Floyd and Brent only keep a couple of states around, so they are the right choice when
+// states are large or the tail is very long; the hash map variant remembers every state it
+// sees, but it only applies the function once per state and finds the cycle in a single pass.
+
+#[derive(Debug, PartialEq)]
+pub struct Cycle {
+    pub tail_length: usize,
+    pub period: usize,
+}
+
+pub fn floyd<T, F>(start: &T, step: F) -> Cycle
+where
+    T: Clone + Eq,
+    F: Fn(&T) -> T,
+{
+    // The hare moves twice as fast as the tortoise, so they meet somewhere in the cycle.
+    let mut tortoise = step(start);
+    let mut hare = step(&step(start));
+
+    while tortoise != hare {
+        tortoise = step(&tortoise);
+        hare = step(&step(&hare));
+    }
+
+    // The distance from the start to the beginning of the cycle is the same as the one from
+    // the meeting point, going around the cycle.
+    let mut tail_length = 0;
+    tortoise = start.clone();
+
+    while tortoise != hare {
+        tortoise = step(&tortoise);
+        hare = step(&hare);
+        tail_length += 1;
+    }
+
+    let mut period = 1;
+    hare = step(&tortoise);
+
+    while tortoise != hare {
+        hare = step(&hare);
+        period += 1;
+    }
+
+    Cycle {
+        tail_length,
+        period,
+    }
+}
+
+pub fn brent<T, F>(start: &T, step: F) -> Cycle
+where
+    T: Clone + Eq,
+    F: Fn(&T) -> T,
+{
+    // The tortoise teleports to the hare every power of two steps, and the hare counts how far
+    // it gets from the tortoise before they meet: that is the period.
+    let mut power = 1;
+    let mut period = 1;
+    let mut tortoise = start.clone();
+    let mut hare = step(start);
+
+    while tortoise != hare {
+        if power == period {
+            tortoise = hare.clone();
+            power *= 2;
+            period = 0;
+        }
+
+        hare = step(&hare);
+        period += 1;
+    }
+
+    // With the hare one period ahead of the tortoise, they meet at the start of the cycle.
+    let mut tail_length = 0;
+    tortoise = start.clone();
+    hare = start.clone();
+
+    for _ in 0..period {
+        hare = step(&hare);
+    }
+
+    while tortoise != hare {
+        tortoise = step(&tortoise);
+        hare = step(&hare);
+        tail_length += 1;
+    }
+
+    Cycle {
+        tail_length,
+        period,
+    }
+}
+
+pub fn with_hash_map<T, F>(start: &T, step: F) -> Cycle
+where
+    T: Clone + Eq + Hash,
+    F: Fn(&T) -> T,
+{
+    let mut seen: HashMap<T, usize> = HashMap::new();
+    let mut current = start.clone();
+    let mut index = 0;
+
+    loop {
+        if let Some(&first_seen) = seen.get(&current) {
+            return Cycle {
+                tail_length: first_seen,
+                period: index - first_seen,
+            };
+        }
+
+        let next = step(&current);
+        seen.insert(current, index);
+        current = next;
+        index += 1;
+    }
+}
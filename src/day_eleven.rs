@@ -1,67 +1,180 @@
-use std::cmp::min;
-use utils::read_input;
+use hex_grid::{parse_directions, Hex, HexDirection};
+use std::collections::HashSet;
+use utils::{read_input, write_output};
 
-struct Position {
-    row: i32,
-    col: i32,
+// Every position the child goes through, starting from the origin.
+fn record_walk(directions: &[HexDirection]) -> Vec<Hex> {
+    let mut walk: Vec<Hex> = Vec::with_capacity(directions.len() + 1);
+    walk.push(Hex::origin());
+
+    for &direction in directions {
+        let next = walk[walk.len() - 1].neighbour(direction);
+        walk.push(next);
+    }
+
+    walk
+}
+
+// Returns the first position of the walk among the furthest ones from the origin.
+fn furthest_position(walk: &[Hex]) -> Hex {
+    let max_distance = walk.iter().map(|h| h.length()).max().unwrap_or(0);
+    *walk.iter().find(|h| h.length() == max_distance).expect(
+        "The walk is empty.",
+    )
 }
 
-impl Position {
-    fn new(row: i32, col: i32) -> Position {
-        Position { row, col }
+// Summarizes a path by grouping consecutive moves in the same direction, e.g. "3 ne, 2 s".
+fn describe_path(path: &[HexDirection]) -> String {
+    let mut groups: Vec<(HexDirection, usize)> = Vec::new();
+
+    for &direction in path {
+        match groups.last_mut() {
+            Some(&mut (last, ref mut count)) if last == direction => *count += 1,
+            _ => groups.push((direction, 1)),
+        }
     }
+
+    let pieces: Vec<String> = groups
+        .iter()
+        .map(|&(direction, count)| format!("{} {}", count, direction.name()))
+        .collect();
+
+    pieces.join(", ")
+}
+
+// Size of the hexagons in the SVG image, from the center to a corner.
+const HEX_SIZE: f64 = 4.0;
+
+// Center of a hexagon in the SVG image. The y axis of SVG points down, like south.
+fn hex_center(hex: &Hex) -> (f64, f64) {
+    let x = HEX_SIZE * 1.5 * hex.q as f64;
+    let y = HEX_SIZE * 3f64.sqrt() * (hex.r as f64 + hex.q as f64 / 2.0);
+
+    (x, y)
 }
 
-fn min_distance(target_pos: &Position) -> i32 {
-    let mut pos = Position::new(target_pos.row.abs(), target_pos.col.abs());
-    let m = min(pos.row, pos.col);
-    pos.row -= m;
-    pos.col -= m;
+fn hex_polygon(hex: &Hex, attributes: &str) -> String {
+    let (cx, cy) = hex_center(hex);
+    let corners: Vec<String> = (0..6)
+        .map(|i| {
+            let angle = f64::from(i) * 60f64.to_radians();
+            format!(
+                "{:.2},{:.2}",
+                cx + HEX_SIZE * angle.cos(),
+                cy + HEX_SIZE * angle.sin()
+            )
+        })
+        .collect();
 
-    m + pos.col + (pos.row as f32 / 2.0).floor() as i32
+    format!(
+        "  <polygon points=\"{}\" {}/>\n",
+        corners.join(" "),
+        attributes
+    )
 }
 
-pub fn day_eleven() {
-    let contents = read_input("data/day_eleven.txt");
-    let directions: Vec<&str> = contents.split(',').collect();
-
-    let mut child_position = Position::new(0, 0);
-    let mut max_distance = 0;
-
-    for direction in directions {
-        match direction {
-            "se" => {
-                child_position.row += 1;
-                child_position.col += 1;
-            }
-            "se\n" => {
-                child_position.row += 1;
-                child_position.col += 1;
-            }
-            "s" => child_position.row += 2,
-            "sw" => {
-                child_position.row += 1;
-                child_position.col -= 1;
-            }
-            "nw" => {
-                child_position.row -= 1;
-                child_position.col -= 1;
-            }
-            "n" => child_position.row -= 2,
-            "ne" => {
-                child_position.row -= 1;
-                child_position.col += 1;
-            }
-            _ => panic!("Unrecognized direction {}.", direction),
-        }
+fn polyline(hexes: &[Hex], attributes: &str) -> String {
+    let points: Vec<String> = hexes
+        .iter()
+        .map(|h| {
+            let (x, y) = hex_center(h);
+            format!("{:.2},{:.2}", x, y)
+        })
+        .collect();
+
+    format!(
+        "  <polyline points=\"{}\" fill=\"none\" {}/>\n",
+        points.join(" "),
+        attributes
+    )
+}
 
-        let distance = min_distance(&child_position);
-        if distance > max_distance {
-            max_distance = distance;
+// Draws the walk on a hex grid: every hex visited is shaded, the walk is a black line and
+// the shortest way back is a dashed one. The hexes on the straight line between the start
+// and the end are outlined. The start is green, the end red and the furthest point reached
+// blue. The dotted outline is the ring of hexes at the furthest distance.
+fn render_walk_svg(walk: &[Hex]) -> String {
+    let start = walk[0];
+    let end = walk[walk.len() - 1];
+    let furthest = furthest_position(walk);
+    let mut way_back: Vec<Hex> = vec![end];
+
+    for direction in end.path_to(&start) {
+        let next = way_back[way_back.len() - 1].neighbour(direction);
+        way_back.push(next);
+    }
+
+    let mut outline = start.ring(furthest.length());
+    outline.push(outline[0]);
+
+    let (mut min_x, mut min_y, mut max_x, mut max_y) = (0f64, 0f64, 0f64, 0f64);
+    for hex in outline.iter() {
+        let (x, y) = hex_center(hex);
+        min_x = min_x.min(x);
+        min_y = min_y.min(y);
+        max_x = max_x.max(x);
+        max_y = max_y.max(y);
+    }
+
+    let margin = 2.0 * HEX_SIZE;
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"{:.2} {:.2} {:.2} {:.2}\">\n",
+        min_x - margin,
+        min_y - margin,
+        max_x - min_x + 2.0 * margin,
+        max_y - min_y + 2.0 * margin
+    );
+
+    let mut visited: HashSet<Hex> = HashSet::new();
+    for hex in walk {
+        if visited.insert(*hex) {
+            svg.push_str(&hex_polygon(hex, "fill=\"#ddd\" stroke=\"#bbb\""));
         }
     }
 
-    let final_distance = min_distance(&child_position);
-    println!("Day 11 part 1. Distance is {}.", final_distance);
+    for hex in start.line_to(&end) {
+        svg.push_str(&hex_polygon(&hex, "fill=\"none\" stroke=\"#c60\""));
+    }
+
+    svg.push_str(&polyline(
+        &outline,
+        "stroke=\"#888\" stroke-dasharray=\"1,3\"",
+    ));
+    svg.push_str(&polyline(walk, "stroke=\"black\" stroke-width=\"0.5\""));
+    svg.push_str(&polyline(
+        &way_back,
+        "stroke=\"#c60\" stroke-dasharray=\"4,2\"",
+    ));
+    svg.push_str(&hex_polygon(&start, "fill=\"green\""));
+    svg.push_str(&hex_polygon(&furthest, "fill=\"blue\""));
+    svg.push_str(&hex_polygon(&end, "fill=\"red\""));
+    svg.push_str("</svg>\n");
+
+    svg
+}
+
+// Renders the walk of the child as an SVG image. Usage: "cargo run 11 svg <output file>".
+pub fn day_eleven_svg(args: &[String]) {
+    let path = args.first().expect("Expected the path of the output file.");
+    let contents = read_input("data/day_eleven.txt");
+    let directions = parse_directions(&contents).expect("Invalid directions.");
+    let walk = record_walk(&directions);
+
+    write_output(path, &render_walk_svg(&walk));
+
+    println!("Day 11. Walk written to {}.", path);
+}
+
+pub fn day_eleven() {
+    let contents = read_input("data/day_eleven.txt");
+    let directions = parse_directions(&contents).expect("Invalid directions.");
+    let walk = record_walk(&directions);
+
+    let child_position = walk[walk.len() - 1];
+    let max_distance = furthest_position(&walk).length();
+    let way_back = child_position.path_to(&Hex::origin());
+
+    println!("Day 11 part 1. Distance is {}.", child_position.length());
     println!("Day 11 part 2. Max distance reached is {}.", max_distance);
+    println!("Day 11. The way back is {}.", describe_path(&way_back));
 }
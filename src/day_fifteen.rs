@@ -1,8 +1,34 @@
+use std::thread;
+use utils::print_timed;
+
+#[derive(Clone)]
 struct Generator {
     factor: u64,
     divisor: u64,
     current_value: u64,
-    starting_value: u64,
+}
+
+// Computes (a * b) % modulus. The product is computed on 128 bits, so any 64-bit modulus is
+// fine.
+fn mod_mul(a: u64, b: u64, modulus: u64) -> u64 {
+    (u128::from(a) * u128::from(b) % u128::from(modulus)) as u64
+}
+
+// Computes (base ^ exp) % modulus by repeated squaring.
+fn mod_pow(mut base: u64, mut exp: u64, modulus: u64) -> u64 {
+    let mut result: u64 = 1 % modulus;
+    base %= modulus;
+
+    while exp > 0 {
+        if exp & 1 == 1 {
+            result = mod_mul(result, base, modulus);
+        }
+
+        base = mod_mul(base, base, modulus);
+        exp >>= 1;
+    }
+
+    result
 }
 
 impl Generator {
@@ -11,27 +37,34 @@ impl Generator {
             factor,
             divisor,
             current_value: starting_value,
-            starting_value,
         }
     }
 
-    fn next(&mut self) -> u64 {
-        self.current_value = (self.current_value * self.factor) % self.divisor;
-        return self.current_value;
+    // Advances the generator by |n| values in logarithmic time: after n steps the current
+    // value is multiplied by factor^n, so we only need to compute that power modulo divisor.
+    fn skip_ahead(&mut self, n: u64) {
+        let multiplier = mod_pow(self.factor, n, self.divisor);
+        self.current_value = mod_mul(self.current_value, multiplier, self.divisor);
     }
 
-    fn reset(&mut self) {
-        self.current_value = self.starting_value;
+    // Only yields the values that are multiples of |multiple|.
+    fn picky(self, multiple: u64) -> impl Iterator<Item = u64> {
+        self.filter(move |n| n % multiple == 0)
     }
+}
 
-    fn next_picky(&mut self, multiple: u64) -> u64 {
-        loop {
-            let n = self.next();
+impl Iterator for Generator {
+    type Item = u64;
 
-            if n % multiple == 0 {
-                return n;
-            }
-        }
+    fn next(&mut self) -> Option<u64> {
+        self.current_value = mod_mul(self.current_value, self.factor, self.divisor);
+        Some(self.current_value)
+    }
+
+    // Random access: skipping values does not require generating them.
+    fn nth(&mut self, n: usize) -> Option<u64> {
+        self.skip_ahead(n as u64);
+        self.next()
     }
 }
 
@@ -43,27 +76,171 @@ fn lowest_bits_match(v1: u64, v2: u64) -> bool {
     b1 == b2
 }
 
-pub fn day_fifteen() {
-    let mut a = Generator::new(16807, 2147483647, 883);
-    let mut b = Generator::new(48271, 2147483647, 879);
-    let mut judge_count = 0;
-    let mut second_judge_count = 0;
-    let ten: u64 = 10;
+// Number of raw values each thread scans at a time when looking for picky values.
+const PICKY_CHUNK_SIZE: u64 = 1 << 20;
+
+fn thread_count() -> u64 {
+    thread::available_parallelism().map_or(1, |n| n.get() as u64)
+}
 
-    for _ in 0..40 * ten.pow(6) {
-        if lowest_bits_match(a.next(), b.next()) {
-            judge_count += 1;
+fn serial_judge(a: &Generator, b: &Generator, pairs: u64) -> usize {
+    a.clone()
+        .zip(b.clone())
+        .take(pairs as usize)
+        .filter(|&(v1, v2)| lowest_bits_match(v1, v2))
+        .count()
+}
+
+fn serial_picky_judge(a: &Generator, b: &Generator, pairs: u64) -> usize {
+    a.clone()
+        .picky(4)
+        .zip(b.clone().picky(8))
+        .take(pairs as usize)
+        .filter(|&(v1, v2)| lowest_bits_match(v1, v2))
+        .count()
+}
+
+// Splits the pairs in one chunk per thread. Each thread jumps directly to the start of its
+// chunk, so no thread has to generate the values that come before it.
+fn parallel_judge(a: &Generator, b: &Generator, pairs: u64) -> usize {
+    let threads = thread_count();
+    let chunk_size = pairs.div_ceil(threads);
+
+    thread::scope(|scope| {
+        let handles: Vec<_> = (0..threads)
+            .map(|i| {
+                let start = (i * chunk_size).min(pairs);
+                let len = chunk_size.min(pairs - start);
+                let mut chunk_a = a.clone();
+                let mut chunk_b = b.clone();
+                chunk_a.skip_ahead(start);
+                chunk_b.skip_ahead(start);
+
+                scope.spawn(move || {
+                    chunk_a
+                        .zip(chunk_b)
+                        .take(len as usize)
+                        .filter(|&(v1, v2)| lowest_bits_match(v1, v2))
+                        .count()
+                })
+            })
+            .collect();
+
+        handles
+            .into_iter()
+            .map(|h| h.join().expect("Judge thread panicked."))
+            .sum()
+    })
+}
+
+// Returns the first |count| values of |generator| that are multiples of |multiple|. We
+// cannot know in advance where the n-th picky value is in the raw sequence, so the threads
+// scan consecutive chunks of the raw sequence, and the results are concatenated in order until
+// we have enough of them.
+fn collect_picky_values(generator: &Generator, multiple: u64, count: usize) -> Vec<u64> {
+    let threads = thread_count();
+    let mut values: Vec<u64> = Vec::with_capacity(count);
+    let mut start: u64 = 0;
+
+    while values.len() < count {
+        let chunks: Vec<Vec<u64>> = thread::scope(|scope| {
+            let handles: Vec<_> = (0..threads)
+                .map(|i| {
+                    let mut chunk = generator.clone();
+                    chunk.skip_ahead(start + i * PICKY_CHUNK_SIZE);
+
+                    scope.spawn(move || {
+                        chunk
+                            .take(PICKY_CHUNK_SIZE as usize)
+                            .filter(|n| n % multiple == 0)
+                            .collect()
+                    })
+                })
+                .collect();
+
+            handles
+                .into_iter()
+                .map(|h| h.join().expect("Generator thread panicked."))
+                .collect()
+        });
+
+        for chunk in chunks {
+            values.extend(chunk);
         }
+
+        start += threads * PICKY_CHUNK_SIZE;
     }
 
-    a.reset();
-    b.reset();
+    values.truncate(count);
+    values
+}
 
-    for _ in 0..5 * ten.pow(6) {
-        if lowest_bits_match(a.next_picky(4), b.next_picky(8)) {
-            second_judge_count += 1;
-        }
+// Gives the same result as serial_picky_judge: pairs are still formed by the n-th picky value
+// of each generator, they are only found in parallel.
+fn parallel_picky_judge(a: &Generator, b: &Generator, pairs: u64) -> usize {
+    let values_a = collect_picky_values(a, 4, pairs as usize);
+    let values_b = collect_picky_values(b, 8, pairs as usize);
+
+    values_a
+        .iter()
+        .zip(values_b.iter())
+        .filter(|&(v1, v2)| lowest_bits_match(*v1, *v2))
+        .count()
+}
+
+fn input_generators() -> (Generator, Generator) {
+    (
+        Generator::new(16807, 2147483647, 883),
+        Generator::new(48271, 2147483647, 879),
+    )
+}
+
+type Judge = fn(&Generator, &Generator, u64) -> usize;
+
+// Runs a judge and prints how long it took.
+fn time_judge(name: &str, judge: Judge, pairs: u64) -> usize {
+    let (a, b) = input_generators();
+    print_timed(
+        &format!("{:>22}", name),
+        || judge(&a, &b, pairs),
+        |count| format!("count {}", count),
+    )
+}
+
+// Compares the running time of the serial and parallel judges. Usage: "cargo run 15 bench".
+pub fn day_fifteen_bench(_args: &[String]) {
+    let ten: u64 = 10;
+    let benchmarks: Vec<(&str, Judge, Judge, u64)> = vec![
+        ("judge", serial_judge, parallel_judge, 40 * ten.pow(6)),
+        (
+            "picky judge",
+            serial_picky_judge,
+            parallel_picky_judge,
+            5 * ten.pow(6),
+        ),
+    ];
+
+    println!("Day 15 benchmark with {} threads.", thread_count());
+
+    for (name, serial, parallel, pairs) in benchmarks {
+        time_judge(&format!("serial {}", name), serial, pairs);
+        time_judge(&format!("parallel {}", name), parallel, pairs);
     }
+}
+
+pub fn day_fifteen() {
+    let (a, b) = input_generators();
+    let ten: u64 = 10;
+
+    // With a single thread, the parallel judges only add overhead.
+    let (judge, picky_judge): (Judge, Judge) = if thread_count() == 1 {
+        (serial_judge, serial_picky_judge)
+    } else {
+        (parallel_judge, parallel_picky_judge)
+    };
+
+    let judge_count = judge(&a, &b, 40 * ten.pow(6));
+    let second_judge_count = picky_judge(&a, &b, 5 * ten.pow(6));
 
     println!("Day 15 part 1. Judge count is {}.", judge_count);
     println!(
@@ -71,3 +248,78 @@ pub fn day_fifteen() {
         second_judge_count
     );
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn example_generators() -> (Generator, Generator) {
+        (
+            Generator::new(16807, 2147483647, 65),
+            Generator::new(48271, 2147483647, 8921),
+        )
+    }
+
+    #[test]
+    fn puzzle_example() {
+        let (a, b) = example_generators();
+
+        assert_eq!(
+            a.take(5).collect::<Vec<u64>>(),
+            vec![1092455, 1181022009, 245556042, 1744312007, 1352636452]
+        );
+        assert_eq!(
+            b.take(5).collect::<Vec<u64>>(),
+            vec![430625591, 1233683848, 1431495498, 137874439, 285222916]
+        );
+    }
+
+    #[test]
+    fn parallel_judges_match_serial() {
+        let (a, b) = example_generators();
+
+        assert_eq!(serial_judge(&a, &b, 5), 1);
+        assert_eq!(serial_picky_judge(&a, &b, 1056), 1);
+
+        for &pairs in [0, 1, 7, 1001, 12345].iter() {
+            assert_eq!(parallel_judge(&a, &b, pairs), serial_judge(&a, &b, pairs));
+            assert_eq!(
+                parallel_picky_judge(&a, &b, pairs),
+                serial_picky_judge(&a, &b, pairs)
+            );
+        }
+    }
+
+    #[test]
+    fn skip_ahead_matches_stepping() {
+        let (a, b) = example_generators();
+
+        for generator in [a, b].iter() {
+            let mut stepped = generator.clone();
+
+            for n in 0..300 {
+                let expected = stepped.next();
+
+                let mut skipped = generator.clone();
+                skipped.skip_ahead(n);
+                assert_eq!(skipped.next(), expected);
+                assert_eq!(generator.clone().nth(n as usize), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn large_divisor() {
+        // The largest prime below 2^64: products no longer fit in 64 bits.
+        let divisor = u64::MAX - 58;
+        let mut generator = Generator::new(divisor - 2, divisor, divisor - 1);
+
+        // (-1) * (-2) = 2, then 2 * (-2) = -4.
+        assert_eq!(generator.next(), Some(2));
+        assert_eq!(generator.next(), Some(divisor - 4));
+
+        let mut skipped = Generator::new(divisor - 2, divisor, divisor - 1);
+        skipped.skip_ahead(1);
+        assert_eq!(skipped.next(), Some(divisor - 4));
+    }
+}
@@ -1,3 +1,7 @@
+use day_ten::{knot_hash, KnotHashConfig};
+use grid_labeling::{label_components, Connectivity, Labeling};
+use utils::{distinct_colour, write_output};
+
 fn hex_to_bin(hex: char) -> String {
     let result: String = String::from(match hex {
         '0' => "0000",
@@ -22,170 +26,169 @@ fn hex_to_bin(hex: char) -> String {
     result
 }
 
-fn run_one_cycle(
-    sequence: &mut Vec<i32>,
-    lengths: &Vec<usize>,
-    position: &mut usize,
-    skip_size: &mut usize,
-) {
-    for length in lengths.iter() {
-        let end_index: usize = (*position + *length - 1) % sequence.len();
-
-        let mut i = *position;
-        let mut j = end_index;
-
-        for _ in 0..length / 2 {
-            let tmp = sequence[i];
-            sequence[i] = sequence[j];
-            sequence[j] = tmp;
-
-            i = (i + 1) % sequence.len();
-            if j == 0 {
-                j = sequence.len() - 1;
-            } else {
-                j -= 1;
-            }
-        }
-
-        *position = (*position + *length + *skip_size) % sequence.len();
-        *skip_size += 1;
-    }
+// Labels the regions of the disk, i.e. groups of used squares adjacent to each other.
+fn label_regions(disk_map: &[String], connectivity: Connectivity) -> Labeling {
+    let grid: Vec<Vec<char>> = disk_map.iter().map(|row| row.chars().collect()).collect();
+    label_components(&grid, connectivity, |&ch| ch == '1')
 }
 
-fn compute_knot_hash(input: &String) -> String {
-    let mut sequence: Vec<i32> = (0..256).collect();
-
-    let new_input = input.as_bytes().to_vec();
-    let mut lengths: Vec<usize> = new_input.iter().map(|x| *x as usize).collect();
-    lengths.push(17);
-    lengths.push(31);
-    lengths.push(73);
-    lengths.push(47);
-    lengths.push(23);
-
-    let mut position: usize = 0;
-    let mut skip_size: usize = 0;
-
-    for _ in 0..64 {
-        run_one_cycle(&mut sequence, &lengths, &mut position, &mut skip_size);
-    }
-
-    let mut dense_hash: String = String::new();
-    let mut block_start = 0;
-
-    while block_start < sequence.len() {
-        let mut dense_hash_item: i32 = sequence[block_start];
+// Builds the grid of the disk: each row is a string of 0s (free) and 1s (used).
+fn build_disk_map(key: &str) -> Vec<String> {
+    let mut disk_map: Vec<String> = Vec::new();
 
-        for i in block_start + 1..block_start + 16 {
-            dense_hash_item = dense_hash_item ^ sequence[i];
-        }
+    for row in 0..128 {
+        let new_input: String = format!("{}-{}", key, row);
+        let hash = knot_hash(&KnotHashConfig::default(), &new_input).expect("Invalid key.");
+        let mut bin_hash = String::new();
 
-        let mut tmp = format!("{:x}", dense_hash_item);
-        if tmp.len() == 1 {
-            tmp.insert(0, '0');
+        for ch in hash.chars() {
+            bin_hash += &hex_to_bin(ch);
         }
 
-        dense_hash.push_str(&tmp);
-        block_start += 16;
+        disk_map.push(bin_hash);
     }
 
-    dense_hash
+    disk_map
 }
 
-fn get_cell_neighbours(
-    disk_region_map: &Vec<Vec<u32>>,
-    square: (usize, usize),
-) -> Vec<(usize, usize)> {
-    let mut result: Vec<(usize, usize)> = Vec::new();
+// Each square of the disk becomes a PIXELS_PER_SQUARE x PIXELS_PER_SQUARE block in the
+// exported images, otherwise they are too small to look at.
+const PIXELS_PER_SQUARE: usize = 4;
 
-    if square.0 > 0 {
-        result.push((square.0 - 1, square.1));
-    }
-    if square.0 < disk_region_map.len() - 1 {
-        result.push((square.0 + 1, square.1));
-    }
+// Writes the used/free grid as a plain PBM image, where used squares are black.
+fn write_pbm(disk_map: &[String], path: &str) {
+    let height = disk_map.len() * PIXELS_PER_SQUARE;
+    let width = disk_map.first().map_or(0, |row| row.len()) * PIXELS_PER_SQUARE;
+    let mut contents = format!("P1\n{} {}\n", width, height);
 
-    if square.1 > 0 {
-        result.push((square.0, square.1 - 1));
-    }
+    for row in disk_map {
+        let mut line = String::new();
 
-    if square.1 < disk_region_map[square.0].len() - 1 {
-        result.push((square.0, square.1 + 1));
+        for ch in row.chars() {
+            for _ in 0..PIXELS_PER_SQUARE {
+                line.push_str(if ch == '1' { "1 " } else { "0 " });
+            }
+        }
+
+        line.pop();
+        line.push('\n');
+
+        for _ in 0..PIXELS_PER_SQUARE {
+            contents.push_str(&line);
+        }
     }
 
-    result
+    write_output(path, &contents);
 }
 
-fn count_regions(disk_map: Vec<String>) -> u32 {
-    let mut region_count: u32 = 2;
-    let mut disk_region_map: Vec<Vec<u32>> = Vec::new();
+// Writes the region-labelled grid as a plain PPM image: free squares are black, and each
+// region has its own colour.
+fn write_ppm(disk_region_map: &[Vec<u32>], path: &str) {
+    let height = disk_region_map.len() * PIXELS_PER_SQUARE;
+    let width = disk_region_map.first().map_or(0, |row| row.len()) * PIXELS_PER_SQUARE;
+    let mut contents = format!("P3\n{} {}\n255\n", width, height);
 
-    for row in disk_map {
-        let mut new_row: Vec<u32> = Vec::new();
+    for row in disk_region_map {
+        let mut line = String::new();
 
-        for ch in row.chars() {
-            if ch == '1' {
-                new_row.push(1);
+        for label in row {
+            let (r, g, b) = if *label == 0 {
+                (0, 0, 0)
             } else {
-                new_row.push(0);
+                distinct_colour(*label)
+            };
+
+            for _ in 0..PIXELS_PER_SQUARE {
+                line.push_str(&format!("{} {} {} ", r, g, b));
             }
         }
 
-        disk_region_map.push(new_row);
-    }
-
-    for row_index in 0..disk_region_map.len() {
-        for col_index in 0..disk_region_map[row_index].len() {
-            if disk_region_map[row_index][col_index] == 1 {
-                let mut queue: Vec<(usize, usize)> = Vec::new();
-                queue.push((row_index, col_index));
+        line.pop();
+        line.push('\n');
 
-                while !queue.is_empty() {
-                    let square = queue.pop().expect("Impossible");
+        for _ in 0..PIXELS_PER_SQUARE {
+            contents.push_str(&line);
+        }
+    }
 
-                    disk_region_map[square.0][square.1] = region_count;
-                    let neighbours = get_cell_neighbours(&disk_region_map, square);
+    write_output(path, &contents);
+}
 
-                    for n in neighbours {
-                        if disk_region_map[n.0][n.1] == 1 {
-                            queue.push(n);
-                        }
-                    }
-                }
+const DEFAULT_KEY: &str = "oundnydw";
+
+// Renders the disk grid. Usage:
+//   cargo run 14 render ascii [key]
+//   cargo run 14 render pbm <output file> [key]
+//   cargo run 14 render ppm <output file> [key]
+// The ASCII mode prints # for used squares and . for free ones, like the puzzle text. The PBM
+// image shows the used and free squares, while the PPM one shows each region in a different
+// colour.
+pub fn day_fourteen_render(args: &[String]) {
+    let mode: &str = args.first().map_or("", |m| m.as_str());
+
+    match mode {
+        "ascii" => {
+            let key = args.get(1).map_or(DEFAULT_KEY, |k| k.as_str());
+
+            for row in build_disk_map(key) {
+                let line: String = row.chars()
+                    .map(|ch| if ch == '1' { '#' } else { '.' })
+                    .collect();
+                println!("{}", line);
+            }
+        }
+        "pbm" | "ppm" => {
+            let path = args.get(1).expect("Expected the path of the output file.");
+            let key = args.get(2).map_or(DEFAULT_KEY, |k| k.as_str());
+            let disk_map = build_disk_map(key);
 
-                region_count += 1;
+            if mode == "pbm" {
+                write_pbm(&disk_map, path);
+            } else {
+                let labeling = label_regions(&disk_map, Connectivity::Four);
+                write_ppm(&labeling.labels, path);
             }
+
+            println!("Day 14. Disk map written to {}.", path);
         }
+        _ => panic!("Usage: \"cargo run 14 render <ascii|pbm|ppm> [output file] [key]\""),
     }
-
-    region_count - 2
 }
 
 pub fn day_fourteen() {
-    let input = String::from("oundnydw-");
+    let disk_map = build_disk_map(DEFAULT_KEY);
     let mut used_count = 0;
-    let mut disk_map: Vec<String> = Vec::new();
-
-    for row in 0..128 {
-        let new_input: String = input.clone() + &row.to_string();
-        let hash = compute_knot_hash(&new_input);
-        let mut bin_hash = String::new();
-
-        for ch in hash.chars() {
-            bin_hash += &hex_to_bin(ch);
-        }
 
-        for ch in bin_hash.chars() {
+    for row in disk_map.iter() {
+        for ch in row.chars() {
             if ch == '1' {
                 used_count += 1;
             }
         }
-
-        disk_map.push(bin_hash);
     }
 
-    let regions = count_regions(disk_map);
+    let labeling = label_regions(&disk_map, Connectivity::Four);
 
     println!("Day 14 part 1. Number of used squares is {}.", used_count);
-    println!("Day 14 part 2. Number of regions is {}.", regions);
+    println!(
+        "Day 14 part 2. Number of regions is {}.",
+        labeling.components.len()
+    );
+
+    if let Some(largest) = labeling.components.iter().max_by_key(|c| c.size) {
+        println!(
+            "Day 14. Largest region is #{} with {} squares, in rows {}-{} and columns {}-{}.",
+            largest.label,
+            largest.size,
+            largest.min_row,
+            largest.max_row,
+            largest.min_col,
+            largest.max_col
+        );
+    }
+
+    println!(
+        "Day 14. Counting diagonal neighbours too, there are {} regions.",
+        label_regions(&disk_map, Connectivity::Eight).components.len()
+    );
 }
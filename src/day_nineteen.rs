@@ -1,5 +1,11 @@
-use utils::read_input;
+use std::collections::HashSet;
+use std::fmt;
+use std::io::{self, Write};
+use std::thread;
+use std::time::Duration;
+use utils::{read_input, write_output};
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 struct Position {
     row: usize,
     col: usize,
@@ -11,6 +17,7 @@ impl Position {
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 enum Direction {
     Down,
     Right,
@@ -18,118 +25,539 @@ enum Direction {
     Left,
 }
 
-fn get_start_position(path: &Vec<&str>) -> Position {
-    let mut pos = Position::new(0, 0);
-    let mut index = 0;
+impl Direction {
+    fn is_vertical(&self) -> bool {
+        *self == Direction::Down || *self == Direction::Up
+    }
 
-    for ch in path[0].chars() {
-        if ch == '|' {
-            pos.col = index;
+    fn opposite(&self) -> Direction {
+        match *self {
+            Direction::Down => Direction::Up,
+            Direction::Right => Direction::Left,
+            Direction::Up => Direction::Down,
+            Direction::Left => Direction::Right,
         }
-
-        index += 1;
     }
 
-    pos
+    // The two directions we can turn to.
+    fn turns(&self) -> [Direction; 2] {
+        if self.is_vertical() {
+            [Direction::Right, Direction::Left]
+        } else {
+            [Direction::Down, Direction::Up]
+        }
+    }
 }
 
-fn get_char(path: &Vec<&str>, pos: &Position) -> char {
-    return path[pos.row].chars().nth(pos.col).unwrap();
+const ALL_DIRECTIONS: [Direction; 4] = [
+    Direction::Down,
+    Direction::Right,
+    Direction::Up,
+    Direction::Left,
+];
+
+// What a character of the diagram does to the packet going through it.
+enum Tube {
+    // Keeps going in the same direction: straight tubes, letters, or a tube crossing another.
+    Straight,
+    // A "+": the packet has to turn.
+    Turn,
+    // A box-drawing corner or junction, connected only in the given directions.
+    Junction(&'static [Direction]),
 }
 
-fn get_right_char(path: &Vec<&str>, pos: &Position) -> char {
-    if pos.col == path[0].len() - 1 {
-        return ' ';
-    }
+fn classify(ch: char) -> Option<Tube> {
+    use self::Direction::*;
+
+    let exits: &'static [Direction] = match ch {
+        ' ' | '\t' => return None,
+        '+' => return Some(Tube::Turn),
+        '┌' | '╔' | '╭' | '┏' => &[Right, Down],
+        '┐' | '╗' | '╮' | '┓' => &[Left, Down],
+        '└' | '╚' | '╰' | '┗' => &[Right, Up],
+        '┘' | '╝' | '╯' | '┛' => &[Left, Up],
+        '├' | '╠' | '┣' => &[Up, Down, Right],
+        '┤' | '╣' | '┫' => &[Up, Down, Left],
+        '┬' | '╦' | '┳' => &[Left, Right, Down],
+        '┴' | '╩' | '┻' => &[Left, Right, Up],
+        '┼' | '╬' | '╋' => &[Down, Right, Up, Left],
+        _ => return Some(Tube::Straight),
+    };
 
-    let new_pos = Position::new(pos.row, pos.col + 1);
-    get_char(path, &new_pos)
+    Some(Tube::Junction(exits))
 }
 
-fn get_left_char(path: &Vec<&str>, pos: &Position) -> char {
-    if pos.col == 0 {
-        return ' ';
+// How well the packet fits into |ch| when moving in |direction|: 0 if it cannot go there at
+// all, 1 if it would be crossing a tube going the other way, 2 if the tube goes its way.
+fn entry_score(ch: char, direction: Direction) -> u8 {
+    match classify(ch) {
+        None => 0,
+        Some(Tube::Junction(exits)) if !exits.contains(&direction.opposite()) => 0,
+        Some(Tube::Straight) => {
+            match ch {
+                '|' | '│' | '║' | '┃' if !direction.is_vertical() => 1,
+                '-' | '─' | '═' | '━' if direction.is_vertical() => 1,
+                _ => 2,
+            }
+        }
+        _ => 2,
     }
-
-    let new_pos = Position::new(pos.row, pos.col - 1);
-    get_char(path, &new_pos)
 }
 
-fn get_up_char(path: &Vec<&str>, pos: &Position) -> char {
-    if pos.row == 0 {
-        return ' ';
+// Whether the tube at |ch| carries on in |direction|. Letters do not tell which way their
+// tube goes, so they never do.
+fn goes_towards(ch: char, direction: Direction) -> bool {
+    match classify(ch) {
+        Some(Tube::Junction(exits)) => exits.contains(&direction),
+        Some(Tube::Straight) => !ch.is_alphabetic() && entry_score(ch, direction) == 2,
+        _ => false,
     }
+}
 
-    let new_pos = Position::new(pos.row - 1, pos.col);
-    get_char(path, &new_pos)
+// How a route through the diagram stops.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Ending {
+    // The packet leaves the diagram from the given position, on its edge.
+    Exit(Position),
+    // There is nowhere to go from the given position.
+    DeadEnd(Position),
+    // The packet is back at the given position, going in the same direction as before.
+    Loop(Position),
+    // There is more than one way to go from the given position.
+    Ambiguous(Position),
 }
 
-fn get_down_char(path: &Vec<&str>, pos: &Position) -> char {
-    if pos.row == path.len() - 1 {
-        return ' ';
+impl fmt::Display for Ending {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (description, position) = match *self {
+            Ending::Exit(position) => ("leaves the diagram", position),
+            Ending::DeadEnd(position) => ("reaches a dead end", position),
+            Ending::Loop(position) => ("loops", position),
+            Ending::Ambiguous(position) => ("reaches an ambiguous junction", position),
+        };
+
+        write!(
+            f,
+            "{} at row {}, column {}",
+            description,
+            position.row,
+            position.col
+        )
     }
+}
 
-    let new_pos = Position::new(pos.row + 1, pos.col);
-    get_char(path, &new_pos)
+struct Route {
+    letters: String,
+    steps: usize,
+    // Every position the packet goes through, in order. Positions where tubes cross can appear
+    // twice.
+    coordinates: Vec<Position>,
+    ending: Ending,
 }
 
-// This assumes we already know that we can move in that direction without
-// going out of bound of the path matrix.
-fn update_position(direction: &Direction, pos: &Position) -> Position {
-    let mut new_pos = Position::new(pos.row, pos.col);
+// The diagram as a grid of characters. Rows can have different lengths: the missing
+// characters at the end of the short ones are treated as spaces.
+struct Diagram {
+    rows: Vec<Vec<char>>,
+    width: usize,
+}
 
-    match *direction {
-        Direction::Down => new_pos.row += 1,
-        Direction::Up => new_pos.row -= 1,
-        Direction::Right => new_pos.col += 1,
-        Direction::Left => new_pos.col -= 1,
-    };
+impl Diagram {
+    fn parse(input: &str) -> Diagram {
+        let mut rows: Vec<Vec<char>> = input
+            .split('\n')
+            .map(|line| line.trim_end_matches('\r').chars().collect())
+            .collect();
 
-    new_pos
-}
+        while rows.last().is_some_and(|row| row.iter().all(|ch| ch.is_whitespace())) {
+            rows.pop();
+        }
 
-pub fn day_nineteen() {
-    let contents = read_input("data/day_nineteen.txt");
-    let path: Vec<&str> = contents.split('\n').collect();
+        let width = rows.iter().map(|row| row.len()).max().unwrap_or(0);
 
-    let mut position = get_start_position(&path);
-    let mut direction = Direction::Down;
-    let mut letters = String::new();
-    let mut steps = 0;
+        Diagram { rows, width }
+    }
 
-    loop {
-        let ch = get_char(&path, &position);
+    fn get(&self, pos: Position) -> char {
+        *self.rows[pos.row].get(pos.col).unwrap_or(&' ')
+    }
 
-        // We completed the path.
-        if ch.is_whitespace() {
-            break;
+    // Returns the position next to |pos| in |direction|, or None if it is out of the diagram.
+    fn neighbour(&self, pos: Position, direction: Direction) -> Option<Position> {
+        match direction {
+            Direction::Down if pos.row + 1 < self.rows.len() => {
+                Some(Position::new(pos.row + 1, pos.col))
+            }
+            Direction::Right if pos.col + 1 < self.width => {
+                Some(Position::new(pos.row, pos.col + 1))
+            }
+            Direction::Up if pos.row > 0 => Some(Position::new(pos.row - 1, pos.col)),
+            Direction::Left if pos.col > 0 => Some(Position::new(pos.row, pos.col - 1)),
+            _ => None,
         }
+    }
+
+    // Finds all the places where a tube comes in from outside of the diagram, with the
+    // direction the packet would be going.
+    fn entry_points(&self) -> Vec<(Position, Direction)> {
+        let mut result: Vec<(Position, Direction)> = Vec::new();
+
+        for row in 0..self.rows.len() {
+            for col in 0..self.width {
+                let pos = Position::new(row, col);
+                let ch = self.get(pos);
+
+                for &direction in ALL_DIRECTIONS.iter() {
+                    // Coming in from outside means the cell behind us is off the diagram. A "+"
+                    // on the edge is a corner, not an entry.
+                    if self.neighbour(pos, direction.opposite()).is_some() || ch == '+' ||
+                        entry_score(ch, direction) < 2
+                    {
+                        continue;
+                    }
 
-        steps += 1; // Count a new step for the second part.
-        if ch.is_uppercase() {
-            letters.push(get_char(&path, &position));
-        } else if ch == '+' {
-            match direction {
-                Direction::Down | Direction::Up => {
-                    if get_right_char(&path, &position) == '-' {
-                        direction = Direction::Right;
-                    } else if get_left_char(&path, &position) == '-' {
-                        direction = Direction::Left;
+                    // A letter on the edge does not tell which way its tube goes.
+                    if ch.is_alphabetic() &&
+                        self.neighbour(pos, direction).is_none_or(|next| {
+                            entry_score(self.get(next), direction) < 2
+                        })
+                    {
+                        continue;
                     }
+
+                    result.push((pos, direction));
                 }
-                Direction::Left | Direction::Right => {
-                    if get_up_char(&path, &position) == '|' {
-                        direction = Direction::Up;
-                    } else if get_down_char(&path, &position) == '|' {
-                        direction = Direction::Down;
+            }
+        }
+
+        result
+    }
+
+    // Picks the direction to leave |pos| in, among |candidates|, preferring tubes that go the
+    // same way over tubes we would be crossing.
+    fn choose_direction(
+        &self,
+        pos: Position,
+        candidates: &[Direction],
+    ) -> Result<Direction, Ending> {
+        let scores: Vec<(Direction, u8)> = candidates
+            .iter()
+            .map(|&direction| {
+                let score = self.neighbour(pos, direction).map_or(0, |next| {
+                    entry_score(self.get(next), direction)
+                });
+                (direction, score)
+            })
+            .collect();
+        let best = scores.iter().map(|&(_, score)| score).max().unwrap_or(0);
+
+        if best == 0 {
+            return Err(Ending::DeadEnd(pos));
+        }
+
+        let mut best_directions = scores.iter().filter(|&&(_, score)| score == best);
+        let direction = best_directions.next().unwrap().0;
+
+        if best_directions.next().is_some() {
+            return Err(Ending::Ambiguous(pos));
+        }
+
+        Ok(direction)
+    }
+
+    // Follows the tubes from |start|, going in |direction|, until the packet leaves the
+    // diagram, gets stuck or starts going around in circles. The packet turns at "+" and at
+    // box-drawing corners, and anywhere else the way ahead is blocked but a single tube
+    // carries on to one side.
+    fn trace(&self, start: Position, mut direction: Direction) -> Route {
+        let mut visited: HashSet<(Position, Direction)> = HashSet::new();
+        let mut letters = String::new();
+        let mut coordinates: Vec<Position> = Vec::new();
+        let mut pos = start;
+
+        let ending = loop {
+            if !visited.insert((pos, direction)) {
+                break Ending::Loop(pos);
+            }
+
+            let ch = self.get(pos);
+            coordinates.push(pos);
+
+            if ch.is_alphabetic() {
+                letters.push(ch);
+            }
+
+            let next_direction = match classify(ch) {
+                Some(Tube::Junction(exits)) if !exits.contains(&direction) => {
+                    let candidates: Vec<Direction> = exits
+                        .iter()
+                        .cloned()
+                        .filter(|&d| d != direction.opposite())
+                        .collect();
+                    self.choose_direction(pos, &candidates)
+                }
+                Some(Tube::Turn) => self.choose_direction(pos, &direction.turns()),
+                _ => Ok(direction),
+            };
+
+            direction = match next_direction {
+                Ok(next_direction) => next_direction,
+                Err(ending) => break ending,
+            };
+
+            match self.neighbour(pos, direction) {
+                Some(next) if entry_score(self.get(next), direction) > 0 => pos = next,
+                None if goes_towards(ch, direction) => break Ending::Exit(pos),
+                _ => {
+                    // Junctions only connect where they say, but the tube under a straight
+                    // tube or a letter may carry on sideways.
+                    if let Some(Tube::Junction(_)) = classify(ch) {
+                        break Ending::DeadEnd(pos);
+                    }
+
+                    match self.choose_direction(pos, &direction.turns()) {
+                        Ok(next_direction) => {
+                            direction = next_direction;
+                            pos = self.neighbour(pos, direction).unwrap();
+                        }
+                        Err(ending) => break ending,
                     }
                 }
             }
+        };
+
+        Route {
+            letters,
+            steps: coordinates.len(),
+            coordinates,
+            ending,
+        }
+    }
+}
+
+// Size of the part of the diagram shown in each frame of the animation, which follows the
+// packet around.
+const VIEW_ROWS: usize = 40;
+const VIEW_COLS: usize = 120;
+const FRAME_MILLISECONDS: u64 = 40;
+
+// First row or column of the view, so that |center| is in the middle when possible.
+fn view_start(center: usize, view_size: usize, total_size: usize) -> usize {
+    center.saturating_sub(view_size / 2).min(total_size.saturating_sub(view_size))
+}
+
+// Draws the part of the diagram around the packet after |step| steps of |route|, with a
+// status bar below. With |ansi|, the tubes already visited are green and the packet is a red
+// block; otherwise the packet is shown as "@" and the frame is plain text.
+fn render_frame(
+    diagram: &Diagram,
+    route: &Route,
+    step: usize,
+    visited: &HashSet<Position>,
+    ansi: bool,
+) -> String {
+    let current = route.coordinates[step];
+    let top = view_start(current.row, VIEW_ROWS, diagram.rows.len());
+    let left = view_start(current.col, VIEW_COLS, diagram.width);
+    let mut frame = String::new();
+
+    for row in top..diagram.rows.len().min(top + VIEW_ROWS) {
+        for col in left..diagram.width.min(left + VIEW_COLS) {
+            let pos = Position::new(row, col);
+            let ch = diagram.get(pos);
+
+            if pos == current {
+                if ansi {
+                    frame.push_str(&format!("\x1b[1;97;41m{}\x1b[0m", ch));
+                } else {
+                    frame.push('@');
+                }
+            } else if ansi && visited.contains(&pos) {
+                frame.push_str(&format!("\x1b[32m{}\x1b[0m", ch));
+            } else {
+                frame.push(ch);
+            }
+        }
+
+        frame.push('\n');
+    }
+
+    let letters: String = route.coordinates[..step + 1]
+        .iter()
+        .map(|&pos| diagram.get(pos))
+        .filter(|ch| ch.is_alphabetic())
+        .collect();
+    let status = format!(
+        "Step {}/{}, row {}, column {}. Letters: {}",
+        step + 1,
+        route.steps,
+        current.row,
+        current.col,
+        letters
+    );
+
+    if ansi {
+        frame.push_str(&format!("\x1b[7m{}\x1b[0m\x1b[K\n", status));
+    } else {
+        frame.push_str(&status);
+        frame.push('\n');
+    }
+
+    frame
+}
+
+// Plays the route of the packet in the terminal, moving it a given number of steps per frame.
+// With an output file, the frames are written there as plain text instead. Usage:
+//   cargo run 19 animate [steps per frame] [output file]
+pub fn day_nineteen_animate(args: &[String]) {
+    let steps_per_frame: usize = args.first().map_or(20, |s| {
+        s.parse().expect("Expected a number of steps per frame.")
+    });
+    assert!(steps_per_frame > 0, "The packet has to move at least one step per frame.");
+    let dump_path = args.get(1);
+
+    let diagram = Diagram::parse(&read_input("data/day_nineteen.txt"));
+    let &(start, direction) = diagram.entry_points().first().expect(
+        "The diagram has no entry point.",
+    );
+    let route = diagram.trace(start, direction);
+
+    let mut frames: Vec<usize> = (0..route.steps).step_by(steps_per_frame).collect();
+    if frames.last() != Some(&(route.steps - 1)) {
+        frames.push(route.steps - 1);
+    }
+
+    let mut visited: HashSet<Position> = HashSet::new();
+    let mut next_visited = 0;
+    let mut dump = String::new();
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+
+    if dump_path.is_none() {
+        // Clear the screen and hide the cursor.
+        write!(out, "\x1b[2J\x1b[?25l").expect("Error writing to the terminal.");
+    }
+
+    for &step in frames.iter() {
+        visited.extend(route.coordinates[next_visited..step + 1].iter().cloned());
+        next_visited = step + 1;
+
+        match dump_path {
+            Some(_) => {
+                dump.push_str(&render_frame(&diagram, &route, step, &visited, false));
+                dump.push('\n');
+            }
+            None => {
+                // Draw over the previous frame rather than scrolling.
+                write!(
+                    out,
+                    "\x1b[H{}",
+                    render_frame(&diagram, &route, step, &visited, true)
+                ).expect("Error writing to the terminal.");
+                out.flush().expect("Error writing to the terminal.");
+                thread::sleep(Duration::from_millis(FRAME_MILLISECONDS));
+            }
+        }
+    }
+
+    match dump_path {
+        Some(path) => {
+            write_output(path, &dump);
+
+            println!("Day 19. {} frames written to {}.", frames.len(), path);
         }
+        None => {
+            writeln!(out, "\x1b[?25hThe packet {}.", route.ending).expect(
+                "Error writing to the terminal.",
+            );
+        }
+    }
+}
+
+pub fn day_nineteen() {
+    let contents = read_input("data/day_nineteen.txt");
+    let diagram = Diagram::parse(&contents);
+
+    let &(start, direction) = diagram.entry_points().first().expect(
+        "The diagram has no entry point.",
+    );
+    let route = diagram.trace(start, direction);
+
+    println!("Day 19 part 1. Solution is {}.", route.letters);
+    println!("Day 19 part 2. Number of steps is {}.", route.steps);
+    println!(
+        "Day 19. The packet enters at row {}, column {} and {}.",
+        route.coordinates[0].row,
+        route.coordinates[0].col,
+        route.ending
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trace(input: &str) -> Route {
+        let diagram = Diagram::parse(input);
+        let &(start, direction) = diagram.entry_points().first().expect("No entry point.");
+        diagram.trace(start, direction)
+    }
+
+    #[test]
+    fn puzzle_example() {
+        let route = trace(concat!(
+            "     |          \n",
+            "     |  +--+    \n",
+            "     A  |  C    \n",
+            " F---|----E|--+ \n",
+            "     |  |  |  D \n",
+            "     +B-+  +--+ \n",
+        ));
 
-        position = update_position(&direction, &position);
+        assert_eq!(route.letters, "ABCDEF");
+        assert_eq!(route.steps, 38);
+        assert_eq!(route.ending, Ending::DeadEnd(Position::new(3, 1)));
     }
 
-    println!("Day 19 part 1. Solution is {}.", letters);
-    println!("Day 19 part 2. Number of steps is {}.", steps);
+    #[test]
+    fn ragged_rows() {
+        let route = trace(" |\n A\n +--B\n");
+
+        assert_eq!(route.letters, "AB");
+        assert_eq!(route.ending, Ending::DeadEnd(Position::new(2, 4)));
+    }
+
+    #[test]
+    fn turns_without_a_corner() {
+        let route = trace(" |\n A--B");
+
+        assert_eq!(route.letters, "AB");
+        assert_eq!(route.steps, 5);
+        assert_eq!(route.ending, Ending::DeadEnd(Position::new(1, 4)));
+    }
+
+    #[test]
+    fn box_drawing() {
+        let route = trace("  │\n  └─A─┐\n      │\n      B\n      │\n");
+
+        assert_eq!(route.letters, "AB");
+        assert_eq!(route.ending, Ending::Exit(Position::new(4, 6)));
+    }
+
+    #[test]
+    fn loop_is_detected() {
+        let route = trace("  |\n+-|-+\n| | A\n+-+ |\n  +-+\n");
+
+        assert_eq!(route.letters, "A");
+        assert_eq!(route.ending, Ending::Loop(Position::new(3, 1)));
+    }
+
+    #[test]
+    fn dead_end() {
+        let route = trace("|\n|\nA\n\n|\n");
+
+        assert_eq!(route.letters, "A");
+        assert_eq!(route.steps, 3);
+        assert_eq!(route.ending, Ending::DeadEnd(Position::new(2, 0)));
+    }
 }
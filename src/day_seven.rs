@@ -1,5 +1,5 @@
 use std::collections::HashMap;
-use utils::read_input;
+use utils::{read_input, write_output};
 
 struct Program {
     name: String,
@@ -15,16 +15,16 @@ impl Program {
             children,
         }
     }
-
-    fn has_child(&self, child: &String) -> bool {
-        self.children.contains(child)
-    }
 }
 
 fn parse_input_programs(filename: &str) -> HashMap<String, Program> {
+    parse_programs(&read_input(filename))
+}
+
+fn parse_programs(contents: &str) -> HashMap<String, Program> {
     let mut programs: HashMap<String, Program> = HashMap::new();
 
-    for line in read_input(filename).split('\n') {
+    for line in contents.split('\n') {
         if line.len() == 0 {
             continue;
         }
@@ -55,123 +55,283 @@ fn parse_input_programs(filename: &str) -> HashMap<String, Program> {
     programs
 }
 
-fn find_root(programs: &HashMap<String, Program>) -> Option<String> {
-    for ref p1 in programs.values() {
-        let mut is_parentless = true;
+// The tower of programs as a tree. Programs are identified by their index, and each one
+// knows its parent and its children. The weights of all subtowers are computed once when the
+// tree is built.
+struct Tower {
+    names: Vec<String>,
+    weights: Vec<i32>,
+    children: Vec<Vec<usize>>,
+    parents: Vec<Option<usize>>,
+    subtower_weights: Vec<i32>,
+    root: usize,
+}
 
-        for ref p2 in programs.values() {
-            if p1.name == p2.name {
-                continue;
+impl Tower {
+    fn from_programs(programs: &HashMap<String, Program>) -> Tower {
+        // Sorting the names keeps the indices, and everything derived from them, stable.
+        let mut names: Vec<String> = programs.values().map(|p| p.name.clone()).collect();
+        names.sort();
+
+        let indices: HashMap<&str, usize> = names
+            .iter()
+            .enumerate()
+            .map(|(i, name)| (name.as_str(), i))
+            .collect();
+        let mut weights: Vec<i32> = Vec::with_capacity(names.len());
+        let mut children: Vec<Vec<usize>> = Vec::with_capacity(names.len());
+        let mut parents: Vec<Option<usize>> = vec![None; names.len()];
+
+        for (index, name) in names.iter().enumerate() {
+            let program = &programs[name];
+            let mut program_children: Vec<usize> = Vec::new();
+
+            for child in program.children.iter() {
+                let child_index = *indices.get(child.as_str()).expect("Unknown child program.");
+                parents[child_index] = Some(index);
+                program_children.push(child_index);
             }
 
-            if p2.has_child(&p1.name) {
-                is_parentless = false;
-                break;
-            }
+            weights.push(program.weight);
+            children.push(program_children);
         }
 
-        if is_parentless {
-            return Some(p1.name.clone());
+        // The root is the only program without a parent.
+        let root = parents.iter().position(|p| p.is_none()).expect(
+            "Could not find root.",
+        );
+
+        let mut tower = Tower {
+            names,
+            weights,
+            children,
+            parents,
+            subtower_weights: Vec::new(),
+            root,
+        };
+
+        // In a pre-order traversal every program comes before the programs on top of it, so
+        // going through it backwards we always know the weights of the children already.
+        tower.subtower_weights = tower.weights.clone();
+        for &index in tower.preorder().iter().rev() {
+            if let Some(parent) = tower.parents[index] {
+                tower.subtower_weights[parent] += tower.subtower_weights[index];
+            }
         }
+
+        tower
     }
 
-    None
-}
+    fn root_name(&self) -> &str {
+        &self.names[self.root]
+    }
 
-fn compute_subtower_weight(child: &String, programs: &HashMap<String, Program>) -> i32 {
-    let child_program = programs.get(child).expect("Nope.");
-    let mut subtower_weight = child_program.weight;
+    // Returns the indices of all programs, each one before the programs on top of it.
+    fn preorder(&self) -> Vec<usize> {
+        let mut result: Vec<usize> = Vec::with_capacity(self.names.len());
+        let mut stack: Vec<usize> = vec![self.root];
 
-    for grandchild in child_program.children.iter() {
-        subtower_weight += compute_subtower_weight(grandchild, programs);
+        while let Some(index) = stack.pop() {
+            result.push(index);
+            stack.extend(self.children[index].iter().rev());
+        }
+
+        result
     }
 
-    subtower_weight
-}
+    // Among the children of |index|, finds the one whose subtower has a different weight from
+    // the others, and returns it together with the weight its subtower should have. |target|
+    // is the weight the subtower of |index| itself should have, if known. Returns None if all
+    // children weigh the same, or if there is no way to tell which one is wrong.
+    fn find_unbalanced_child(&self, index: usize, target: Option<i32>) -> Option<(usize, i32)> {
+        let children = &self.children[index];
+        let weights: Vec<i32> = children.iter().map(|&c| self.subtower_weights[c]).collect();
 
-fn all_weights_the_same(weights: &Vec<i32>) -> bool {
-    if weights.is_empty() {
-        return true;
-    }
+        if self.is_balanced(index) {
+            return None;
+        }
+
+        // With at least three children, the odd one out is the one whose weight appears once.
+        if weights.len() > 2 {
+            let odd_index = (0..weights.len()).find(|&i| {
+                weights.iter().filter(|&&w| w == weights[i]).count() == 1
+            })?;
+
+            return Some((children[odd_index], weights[(odd_index + 1) % weights.len()]));
+        }
+
+        // With only two children either could be the wrong one. Fixing the wrong program
+        // changes the weight of every subtower containing it by the same amount, so if we know
+        // how much the subtower of |index| is off, the wrong child is the one which that
+        // amount brings to the weight of the other.
+        if let Some(target) = target {
+            let difference = target - self.subtower_weights[index];
+
+            return (0..2)
+                .find(|&i| weights[i] + difference == weights[1 - i])
+                .map(|i| (children[i], weights[1 - i]));
+        }
 
-    let shared_item = weights[0];
-    for item in weights {
-        if *item != shared_item {
-            return false;
+        // Otherwise the subtower of one of them may be unbalanced itself: since only one
+        // program is wrong, that one must contain it.
+        for (i, &child) in children.iter().enumerate() {
+            if !self.is_balanced(child) {
+                return Some((child, weights[1 - i]));
+            }
         }
+
+        None
     }
 
-    true
-}
+    // Returns true if all the subtowers on top of |index| have the same weight.
+    fn is_balanced(&self, index: usize) -> bool {
+        let weights: Vec<i32> = self.children[index]
+            .iter()
+            .map(|&c| self.subtower_weights[c])
+            .collect();
 
-fn all_the_same_but_one(weights: &Vec<i32>) -> bool {
-    if weights.is_empty() {
-        return false;
+        weights.windows(2).all(|w| w[0] == w[1])
     }
 
-    for i in 0..weights.len() {
-        let shared_item = weights[(i + 1) % weights.len()];
-        let mut all_the_same = true;
+    // Only one program has the wrong weight. Starting from the root, we keep following the
+    // child whose subtower weighs differently from its siblings, along with the weight that
+    // subtower should have. When the children of the current program are balanced, the current
+    // program itself is the wrong one.
+    fn find_correct_weight(&self) -> Option<WeightCorrection> {
+        let mut current = self.root;
+        let mut target: Option<i32> = None;
+
+        while !self.is_balanced(current) {
+            let (child, child_target) = self.find_unbalanced_child(current, target)?;
+            current = child;
+            target = Some(child_target);
+        }
 
-        for j in 0..weights.len() {
-            if i == j {
-                continue;
-            }
+        // The whole tower being balanced means there is nothing to correct.
+        let target = target?;
+        let weight = self.weights[current];
 
-            if weights[j] != shared_item {
-                all_the_same = false;
-                break;
+        Some(WeightCorrection {
+            index: current,
+            name: self.names[current].clone(),
+            current_weight: weight,
+            required_weight: weight + target - self.subtower_weights[current],
+        })
+    }
+
+    // Exports the tower in the DOT format of GraphViz. Each program is labelled with its own
+    // weight and the weight of its subtower; the program with the wrong weight, if any, is
+    // highlighted in red.
+    fn to_dot(&self, unbalanced: Option<usize>) -> String {
+        let mut dot = String::from("digraph tower {\n    rankdir=BT;\n    node [shape=box];\n");
+
+        for index in self.preorder() {
+            let style = if Some(index) == unbalanced {
+                ", style=filled, fillcolor=red"
+            } else {
+                ""
+            };
+
+            dot.push_str(&format!(
+                "    \"{}\" [label=\"{}\\nweight {}\\ntower {}\"{}];\n",
+                self.names[index],
+                self.names[index],
+                self.weights[index],
+                self.subtower_weights[index],
+                style
+            ));
+
+            if let Some(parent) = self.parents[index] {
+                dot.push_str(&format!(
+                    "    \"{}\" -> \"{}\";\n",
+                    self.names[index],
+                    self.names[parent]
+                ));
             }
         }
 
-        if all_the_same {
-            return true;
-        }
+        dot.push_str("}\n");
+        dot
     }
+}
 
-    return false;
+// The program whose weight is wrong, and the weight it should have to balance the tower.
+struct WeightCorrection {
+    index: usize,
+    name: String,
+    current_weight: i32,
+    required_weight: i32,
 }
 
-fn find_correct_weight(programs: HashMap<String, Program>) -> Option<usize> {
-    let root_name: String = find_root(&programs).expect("Could not find root.");
-    let mut queue: Vec<&String> = Vec::new();
+// Exports the tower to a GraphViz file. Usage: "cargo run 7 dot <output file>".
+pub fn day_seven_dot(args: &[String]) {
+    let path = args.first().expect("Expected the path of the output file.");
+    let tower = Tower::from_programs(&parse_input_programs("data/day_seven.txt"));
+    let unbalanced = tower.find_correct_weight().map(|c| c.index);
 
-    queue.push(&root_name);
+    write_output(path, &tower.to_dot(unbalanced));
 
-    while !queue.is_empty() {
-        let node_name = queue.pop().expect("Impossible.");
-        let node = programs.get(node_name).expect("Impossible.");
-        let mut weights: Vec<i32> = Vec::new();
+    println!("Day 7. Tower written to {}.", path);
+}
 
-        for child in node.children.iter() {
-            weights.push(compute_subtower_weight(&child, &programs));
-        }
+pub fn day_seven() {
+    let tower = Tower::from_programs(&parse_input_programs("data/day_seven.txt"));
+
+    println!(
+        "Day 7 part 1. Root of the tree is program {}.",
+        tower.root_name()
+    );
+
+    let correction = tower.find_correct_weight().expect("Could not find correct weight.");
+    println!(
+        "Day 7 part 2. Program {} weighs {}, correct weight is {}.",
+        correction.name,
+        correction.current_weight,
+        correction.required_weight
+    );
+}
 
-        if !all_weights_the_same(&weights) {
-            for child in node.children.iter() {
-                queue.push(child);
-            }
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        if !all_weights_the_same(&weights) && all_the_same_but_one(&weights) {
-            println!("{:?}", weights);
-            println!("{:?}", node.children);
+    fn correction(contents: &str) -> (String, i32, i32) {
+        let tower = Tower::from_programs(&parse_programs(contents));
+        let correction = tower.find_correct_weight().expect("Could not find correct weight.");
 
-            for child in node.children.iter() {
-                let child_node = programs.get(child).expect("Nope");
-                println!("Child {} has weight {}", child, child_node.weight);
-            }
-        }
+        (
+            correction.name,
+            correction.current_weight,
+            correction.required_weight,
+        )
     }
-    None
-}
 
-pub fn day_seven() {
-    let all_programs = parse_input_programs("data/day_seven.txt");
+    #[test]
+    fn puzzle_example() {
+        let contents = "pbga (66)\nxhth (57)\nebii (61)\nhavc (66)\nktlj (57)\n\
+                        fwft (72) -> ktlj, cntj, xhth\nqoyq (66)\npadx (45) -> pbga, havc, qoyq\n\
+                        tknk (41) -> ugml, padx, fwft\njptl (61)\nugml (68) -> gyxo, ebii, jptl\n\
+                        gyxo (61)\ncntj (57)\n";
+        let tower = Tower::from_programs(&parse_programs(contents));
 
-    let root_name: String = find_root(&all_programs).expect("Could not find root.");
-    println!("Day 7 part 1. Root of the tree is program {}.", root_name);
+        assert_eq!("tknk", tower.root_name());
+        assert_eq!((String::from("ugml"), 68, 60), correction(contents));
+    }
+
+    #[test]
+    fn wrong_program_under_two_children() {
+        // Program a has two children weighing differently, and only the weight its subtower
+        // should have tells that e is the wrong one.
+        let contents = "root (10) -> a, b, c\na (1) -> d, e\nb (11)\nc (11)\nd (5)\ne (6)\n";
+
+        assert_eq!((String::from("e"), 6, 5), correction(contents));
+    }
+
+    #[test]
+    fn balanced_tower() {
+        let contents = "root (10) -> a, b\na (3)\nb (3)\n";
+        let tower = Tower::from_programs(&parse_programs(contents));
 
-    let result = find_correct_weight(all_programs).expect("Could not find correct weight.");
-    println!("Day 7 part 2. Correct weight is {}.", result);
+        assert!(tower.find_correct_weight().is_none());
+    }
 }
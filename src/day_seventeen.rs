@@ -1,41 +1,144 @@
-pub fn day_seventeen() {
-    let steps: usize = 356;
-    let mut current_position: usize = 0;
-    let mut circular_buffer: Vec<u32> = Vec::new();
-    circular_buffer.push(0);
+// A spinlock stepping forward |steps| times before each insertion, for |insertions|
+// insertions. Queries only look at the positions at which values are inserted, which is much
+// faster than keeping the whole buffer around, since inserting in the middle of a vector is
+// linear in its length.
+struct Spinlock {
+    steps: usize,
+    insertions: u32,
+}
 
-    for i in 1..2018 {
-        current_position = (current_position + steps) % circular_buffer.len();
-        current_position += 1;
-        circular_buffer.insert(current_position, i);
+impl Spinlock {
+    fn new(steps: usize, insertions: u32) -> Spinlock {
+        Spinlock { steps, insertions }
     }
 
+    fn len(&self) -> usize {
+        self.insertions as usize + 1
+    }
+
+    // Calls |f| with each inserted value and the position it is inserted at, without
+    // building the buffer.
+    fn for_each_insertion<F: FnMut(u32, usize)>(&self, mut f: F) {
+        let mut current_position: usize = 0;
+
+        for i in 1..self.insertions + 1 {
+            current_position = (current_position + self.steps) % i as usize + 1;
+            f(i, current_position);
+        }
+    }
+
+    // Returns the value found at |position| in the final buffer. We find where the spinlock
+    // ends, then undo the insertions from the last one: each insertion before the position we
+    // are looking at shifted it forward by one, and the first one made exactly there inserted
+    // the value we want.
+    fn value_at(&self, position: usize) -> u32 {
+        let mut position = position % self.len();
+        let mut current_position: usize = 0;
+        self.for_each_insertion(|_, p| current_position = p);
+
+        for i in (1..self.insertions + 1).rev() {
+            if current_position == position {
+                return i;
+            }
+            if current_position < position {
+                position -= 1;
+            }
+
+            // Before inserting i, the spinlock was at the only position in [0, i) that leads
+            // to the current one.
+            let i = i as usize;
+            current_position = (current_position + 2 * i - 1 - self.steps % i) % i;
+        }
+
+        // Only 0 is left in the buffer.
+        0
+    }
+
+    // Returns the position of |value| in the final buffer. Each insertion before the value
+    // shifts it forward by one, so we only need to track where it is.
+    fn position_of(&self, value: u32) -> Option<usize> {
+        if value > self.insertions {
+            return None;
+        }
+
+        let mut result = 0;
+        self.for_each_insertion(|i, p| {
+            if i == value {
+                result = p;
+            } else if i > value && p <= result {
+                result += 1;
+            }
+        });
+
+        Some(result)
+    }
+
+    // Returns the value right after |value| in the final buffer.
+    fn value_after(&self, value: u32) -> Option<u32> {
+        if value > self.insertions {
+            return None;
+        }
+
+        let position = self.position_of(value)?;
+        Some(self.value_at((position + 1) % self.len()))
+    }
+}
+
+pub fn day_seventeen() {
+    let steps: usize = 356;
+
+    println!(
+        "Day 17 part 1. Example number after 2017 is {}.",
+        Spinlock::new(3, 2017).value_after(2017).unwrap()
+    );
     println!(
         "Day 17 part 1. The number after 2017 is {}.",
-        circular_buffer[(current_position + 1) % circular_buffer.len()]
+        Spinlock::new(steps, 2017).value_after(2017).unwrap()
     );
 
-    // Part 2. The number we are looking for is the last one that will be inserted at position 1,
-    // since 0 remains at position 0. To speed up things, we avoid inserting into the buffer at
-    // all, and we simply simulate the update of the current position by keeping track of what
-    // the length of the buffer would be. This allows the solution to be found in a few seconds
-    // rather than several hours.
-    current_position = 0;
-    let mut fake_len = 1;
-
     let ten: u32 = 10;
-    let iterations = ten.pow(6) * 50;
-    let mut solution = 0;
+    let spinlock = Spinlock::new(steps, ten.pow(6) * 50);
 
-    for i in 1..iterations + 1 {
-        current_position = (current_position + steps) % fake_len;
-        current_position += 1;
-        fake_len += 1;
+    println!(
+        "Day 17 part 2. Solution is {}.",
+        spinlock.value_after(0).unwrap()
+    );
+    println!(
+        "Day 17. At the end of part 2, 2017 is at position {}.",
+        spinlock.position_of(2017).unwrap()
+    );
+}
 
-        if current_position == 1 {
-            solution = i;
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn build_buffer(spinlock: &Spinlock) -> Vec<u32> {
+        let mut buffer: Vec<u32> = vec![0];
+        spinlock.for_each_insertion(|i, position| buffer.insert(position, i));
+        buffer
+    }
+
+    #[test]
+    fn queries_match_buffer() {
+        for steps in 0..8 {
+            for insertions in 0..40 {
+                let spinlock = Spinlock::new(steps, insertions);
+                let buffer = build_buffer(&spinlock);
+
+                for (position, &value) in buffer.iter().enumerate() {
+                    let next = buffer[(position + 1) % buffer.len()];
+                    assert_eq!(spinlock.value_at(position), value);
+                    assert_eq!(spinlock.position_of(value), Some(position));
+                    assert_eq!(spinlock.value_after(value), Some(next));
+                }
+                assert_eq!(spinlock.value_after(insertions + 1), None);
+            }
         }
     }
 
-    println!("Day 17 part 2. Solution is {}.", solution);
+    #[test]
+    fn puzzle_example() {
+        assert_eq!(Spinlock::new(3, 2017).value_after(2017), Some(638));
+    }
 }
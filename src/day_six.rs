@@ -1,5 +1,5 @@
-use std::collections::HashSet;
-use utils::read_input;
+use cycle_detection::{brent, floyd, with_hash_map, Cycle};
+use utils::{print_timed, read_input};
 
 fn read_integers(filename: &str) -> Vec<i32> {
     let mut result: Vec<i32> = Vec::new();
@@ -29,41 +29,62 @@ fn find_max_index(nums: &Vec<i32>) -> usize {
     max_index
 }
 
-fn one_cycle(mut nums: Vec<i32>) -> (i32, Vec<i32>) {
-    let mut configs: HashSet<Vec<i32>> = HashSet::new();
-    let mut count_steps = 0;
+// Performs one redistribution cycle: the blocks of the largest bank are spread one by one
+// over the following banks.
+fn redistribute(banks: &[i32]) -> Vec<i32> {
+    let mut nums = banks.to_vec();
+    let max_index = find_max_index(&nums);
+    let mut blocks = nums[max_index];
+    let mut index = (max_index + 1) % (nums.len());
+    nums[max_index] = 0;
 
-    loop {
-        if configs.contains(&nums) {
-            break;
-        }
+    while blocks > 0 {
+        nums[index] += 1;
+        blocks -= 1;
+        index = (index + 1) % (nums.len());
+    }
 
-        configs.insert(nums.clone());
-        let max_index = find_max_index(&nums);
-        let mut blocks = nums[max_index];
-        let mut index = (max_index + 1) % (nums.len());
-        nums[max_index] = 0;
+    nums
+}
 
-        while blocks > 0 {
-            nums[index] += 1;
-            blocks -= 1;
-            index = (index + 1) % (nums.len());
-        }
+// Runs a cycle detector and prints how long it took.
+fn time_detector<F: FnOnce() -> Cycle>(name: &str, detector: F) -> Cycle {
+    print_timed(&format!("{:>8}", name), detector, |cycle| {
+        format!(
+            "tail of {} states, period of {} states,",
+            cycle.tail_length,
+            cycle.period
+        )
+    })
+}
 
-        count_steps += 1;
-    }
+// Compares the running time of the cycle detectors on the input. Usage: "cargo run 6 bench".
+pub fn day_six_bench(_args: &[String]) {
+    let nums: Vec<i32> = read_integers("data/day_six.txt");
+    let step = |banks: &Vec<i32>| redistribute(banks);
+
+    let results = [
+        time_detector("Floyd", || floyd(&nums, step)),
+        time_detector("Brent", || brent(&nums, step)),
+        time_detector("hash map", || with_hash_map(&nums, step)),
+    ];
 
-    (count_steps, nums)
+    assert!(
+        results.windows(2).all(|w| w[0] == w[1]),
+        "Cycle detectors disagree."
+    );
 }
 
 pub fn day_six() {
     let nums: Vec<i32> = read_integers("data/day_six.txt");
-    let (count_steps, new_config) = one_cycle(nums);
-    let (count_steps_two, _) = one_cycle(new_config);
+
+    // The first configuration seen twice is the first one of the cycle, and we see it again
+    // after going through the tail and the whole cycle once.
+    let cycle = with_hash_map(&nums, |banks| redistribute(banks));
 
     println!(
         "Day 6 part 1. It took {} steps to return to a known configuration.",
-        count_steps
+        cycle.tail_length + cycle.period
     );
-    println!("Day 6 part 2. It took {} steps.", count_steps_two);
+    println!("Day 6 part 2. It took {} steps.", cycle.period);
 }
@@ -1,116 +1,354 @@
 use utils::read_input;
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum DanceMove {
+    Spin(usize),
+    Exchange(usize, usize),
+    Partner(char, char),
+}
+
+// Splits the argument of exchanges and partners, e.g. "3/4", in its two halves.
+fn split_pair(command: &str) -> Result<(&str, &str), String> {
+    let pieces: Vec<&str> = command[1..].split('/').map(|p| p.trim()).collect();
+
+    if pieces.len() != 2 || pieces[0].is_empty() || pieces[1].is_empty() {
+        return Err(format!("Expected two arguments in move \"{}\".", command));
+    }
+
+    Ok((pieces[0], pieces[1]))
+}
+
+fn parse_position(command: &str, position: &str) -> Result<usize, String> {
+    position.parse().map_err(|_| {
+        format!("Invalid position \"{}\" in move \"{}\".", position, command)
+    })
+}
+
+fn parse_dancer(command: &str, dancer: &str) -> Result<char, String> {
+    let mut chars = dancer.chars();
+
+    match (chars.next(), chars.next()) {
+        (Some(ch), None) => Ok(ch),
+        _ => Err(format!(
+            "Invalid dancer \"{}\" in move \"{}\".",
+            dancer,
+            command
+        )),
+    }
+}
+
+impl DanceMove {
+    fn parse(command: &str) -> Result<DanceMove, String> {
+        match command.chars().next() {
+            Some('s') => Ok(DanceMove::Spin(parse_position(command, command[1..].trim())?)),
+            Some('x') => {
+                let (a, b) = split_pair(command)?;
+                Ok(DanceMove::Exchange(
+                    parse_position(command, a)?,
+                    parse_position(command, b)?,
+                ))
+            }
+            Some('p') => {
+                let (a, b) = split_pair(command)?;
+                Ok(DanceMove::Partner(
+                    parse_dancer(command, a)?,
+                    parse_dancer(command, b)?,
+                ))
+            }
+            _ => Err(format!("Unrecognized move \"{}\".", command)),
+        }
+    }
+}
+
+// Parses a comma-separated list of moves. Errors report which move is invalid.
+fn parse_moves(input: &str) -> Result<Vec<DanceMove>, String> {
+    let mut moves: Vec<DanceMove> = Vec::new();
+
+    for (index, command) in input.split(',').enumerate() {
+        let command = command.trim();
+        if command.is_empty() {
+            continue;
+        }
+
+        let dance_move = DanceMove::parse(command).map_err(|e| format!("Move #{}: {}", index, e))?;
+        moves.push(dance_move);
+    }
+
+    Ok(moves)
+}
 
 struct Dance {
     dancers: Vec<char>,
 }
 
 impl Dance {
+    // The puzzle has sixteen dancers, named from a to p.
     fn new() -> Dance {
-        let d: Vec<char> = [
-            'a',
-            'b',
-            'c',
-            'd',
-            'e',
-            'f',
-            'g',
-            'h',
-            'i',
-            'j',
-            'k',
-            'l',
-            'm',
-            'n',
-            'o',
-            'p',
-        ].to_vec();
-
-        Dance { dancers: d }
-    }
-
-    fn spin(&mut self, x: u32) {
-        for _ in 0..x {
-            let dancer = match self.dancers.pop() {
-                Some(item) => item,
-                None => panic!("Not enough dancers to spin."),
-            };
-            self.dancers.insert(0, dancer);
+        Dance::with_dancers(16)
+    }
+
+    // Creates a line of |count| dancers, named with the first |count| letters of the alphabet.
+    fn with_dancers(count: usize) -> Dance {
+        if count == 0 || count > 26 {
+            panic!("Expected between 1 and 26 dancers, got {}.", count);
         }
+
+        Dance::with_symbols((b'a'..b'a' + count as u8).map(|b| b as char).collect())
+    }
+
+    // Creates a line of dancers with arbitrary names, which must all be different.
+    fn with_symbols(symbols: Vec<char>) -> Dance {
+        for (i, symbol) in symbols.iter().enumerate() {
+            if symbols[i + 1..].contains(symbol) {
+                panic!("Dancer {} appears more than once.", symbol);
+            }
+        }
+
+        Dance { dancers: symbols }
+    }
+
+    // Checks that the moves only name positions and dancers of this line, since performing
+    // or compiling them assumes they do. Errors report which move is invalid.
+    fn validate(&self, moves: &[DanceMove]) -> Result<(), String> {
+        let len = self.dancers.len();
+
+        for (index, dance_move) in moves.iter().enumerate() {
+            match *dance_move {
+                DanceMove::Spin(_) => {}
+                DanceMove::Exchange(a, b) => {
+                    if let Some(p) = [a, b].iter().find(|&&p| p >= len) {
+                        return Err(format!(
+                            "Move #{}: position {} is outside the line of {} dancers.",
+                            index,
+                            p,
+                            len
+                        ));
+                    }
+                }
+                DanceMove::Partner(a, b) => {
+                    if let Some(d) = [a, b].iter().find(|d| !self.dancers.contains(d)) {
+                        return Err(format!("Move #{}: there is no dancer {}.", index, d));
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn spin(&mut self, x: usize) {
+        let len = self.dancers.len();
+        self.dancers.rotate_right(x % len);
     }
 
     fn exchange(&mut self, pos_a: usize, pos_b: usize) {
         self.dancers.swap(pos_a, pos_b);
     }
 
-    fn partner(&mut self, a: char, b: char) {
-        let pos_a = match self.dancers.iter().position(|&x| x == a) {
+    fn find_dancer(&self, dancer: char) -> usize {
+        match self.dancers.iter().position(|&x| x == dancer) {
             Some(p) => p,
-            None => panic!("Dancer {} not found.", a),
-        };
-        let pos_b = match self.dancers.iter().position(|&x| x == b) {
-            Some(p) => p,
-            None => panic!("Dancer {} not found.", b),
-        };
+            None => panic!("Dancer {} not found.", dancer),
+        }
+    }
+
+    fn partner(&mut self, a: char, b: char) {
+        let pos_a = self.find_dancer(a);
+        let pos_b = self.find_dancer(b);
 
         self.exchange(pos_a, pos_b);
     }
 
-    fn final_order(&self) -> String {
-        let mut res: String = String::with_capacity(16);
+    fn perform(&mut self, dance_move: &DanceMove) {
+        match *dance_move {
+            DanceMove::Spin(x) => self.spin(x),
+            DanceMove::Exchange(a, b) => self.exchange(a, b),
+            DanceMove::Partner(a, b) => self.partner(a, b),
+        }
+    }
+
+    // Compiles the moves for this line of dancers; names in the compiled dance are indices
+    // in the current line. The moves must have been validated for this line.
+    fn compile(&self, moves: &[DanceMove]) -> DancePermutation {
+        let size = self.dancers.len();
+        let mut dance = DancePermutation::identity(size);
 
-        for i in 0..self.dancers.len() {
-            res.push(self.dancers[i]);
+        for dance_move in moves {
+            match *dance_move {
+                DanceMove::Spin(x) => dance.positions.rotate_right(x % size),
+                DanceMove::Exchange(a, b) => dance.positions.swap(a, b),
+                DanceMove::Partner(a, b) => {
+                    let name_a = self.find_dancer(a);
+                    let name_b = self.find_dancer(b);
+                    let dancer_a = dance.names.iter().position(|&n| n == name_a).unwrap();
+                    let dancer_b = dance.names.iter().position(|&n| n == name_b).unwrap();
+                    dance.names.swap(dancer_a, dancer_b);
+                }
+            }
+        }
+
+        dance
+    }
+
+    // Performs the whole dance |repetitions| times.
+    fn repeat(&mut self, moves: &[DanceMove], repetitions: u64) {
+        let compiled = self.compile(moves);
+        let start: Vec<usize> = (0..self.dancers.len()).collect();
+        let final_line = compiled.power(repetitions).apply(&start);
+
+        self.dancers = final_line.iter().map(|&n| self.dancers[n]).collect();
+    }
+}
+
+impl fmt::Display for Dance {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for dancer in self.dancers.iter() {
+            write!(f, "{}", dancer)?;
         }
 
-        res
+        Ok(())
+    }
+}
+
+// A dance compiled into two permutations. Spins and exchanges only depend on the positions
+// of the dancers, while partners only depend on their names, so the two kinds of moves commute
+// and can be collected separately: |positions[i]| is the position, before the dance, of the
+// dancer that ends up at position i, and |names[k]| is the name taken by the dancer that was
+// named k before the dance. Names are indices in the initial line.
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct DancePermutation {
+    positions: Vec<usize>,
+    names: Vec<usize>,
+}
+
+impl DancePermutation {
+    fn identity(size: usize) -> DancePermutation {
+        DancePermutation {
+            positions: (0..size).collect(),
+            names: (0..size).collect(),
+        }
+    }
+
+    // Returns the dance equivalent to performing |self| and then |other|.
+    fn then(&self, other: &DancePermutation) -> DancePermutation {
+        DancePermutation {
+            positions: other.positions.iter().map(|&p| self.positions[p]).collect(),
+            names: self.names.iter().map(|&n| other.names[n]).collect(),
+        }
+    }
+
+    // Returns the dance equivalent to performing |self| |n| times, by repeated squaring.
+    fn power(&self, mut n: u64) -> DancePermutation {
+        let mut result = DancePermutation::identity(self.positions.len());
+        let mut square = self.clone();
+
+        while n > 0 {
+            if n & 1 == 1 {
+                result = result.then(&square);
+            }
+
+            square = square.then(&square);
+            n >>= 1;
+        }
+
+        result
+    }
+
+    // Performs the dance on a line of dancers, each identified by the index of their name.
+    fn apply(&self, line: &[usize]) -> Vec<usize> {
+        self.positions.iter().map(|&p| self.names[line[p]]).collect()
     }
 }
 
 pub fn day_sixteen() {
-    let mut dance = Dance::new();
     let input_content = read_input("data/day_sixteen.txt");
+    let moves = parse_moves(&input_content).expect("Invalid dance.");
+    let example_moves = parse_moves("s1,x3/4,pe/b").expect("Invalid dance.");
+    Dance::new().validate(&moves).expect("Invalid dance.");
+    Dance::with_dancers(5).validate(&example_moves).expect("Invalid dance.");
 
-    // If the initial position is iteration 0, by using a HashSet we find that dance positions
-    // repeat themselves after 30 iterations. 10**9 % 30 = 10. So we only need 10 iterations of
-    // the dance to find the final position after one billion iterations.
-    for count in 1..11 {
-        for command in input_content.split(',') {
-            if command.is_empty() {
-                continue;
-            }
+    let mut example = Dance::with_dancers(5);
+    for dance_move in example_moves.iter() {
+        example.perform(dance_move);
+    }
+    println!("Day 16 part 1. Example order of the dancers is {}.", example);
 
-            match command.chars().nth(0).unwrap() {
-                's' => {
-                    let spin_num: u32 = command[1..].parse().expect("Not a number.");
-                    dance.spin(spin_num);
-                }
-                'x' => {
-                    let pieces: Vec<&str> = command[1..].split('/').collect();
-                    let pos_a: usize = pieces[0].trim().parse().expect("Not a number.");
-                    let pos_b: usize = pieces[1].trim().parse().expect("Not a number.");
-                    dance.exchange(pos_a, pos_b);
-                }
-                'p' => {
-                    let pieces: Vec<&str> = command[1..].split('/').collect();
-                    let a: &str = pieces[0].trim();
-                    let b: &str = pieces[1].trim();
-                    dance.partner(a.chars().next().unwrap(), b.chars().next().unwrap());
-                }
-                _ => panic!("Unrecognized command {}", command),
-            };
+    let mut dance = Dance::new();
+    for dance_move in moves.iter() {
+        dance.perform(dance_move);
+    }
+    println!("Day 16 part 1. Final order of the dancers is {}.", dance);
+
+    let mut example = Dance::with_dancers(5);
+    example.repeat(&example_moves, 2);
+    println!("Day 16 part 2. Example order of the dancers is {}.", example);
+
+    // Repeating the dance a billion times, or any other number of times, only takes a
+    // logarithmic number of compositions once the dance is compiled into a permutation.
+    let mut dance = Dance::new();
+    let ten: u64 = 10;
+    dance.repeat(&moves, ten.pow(9));
+
+    println!("Day 16 part 2. Final order of the dancers is {}.", dance);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cycle_detection::brent;
+
+    // Repeats the dance without exponentiation: the dances form a finite group, so sooner or
+    // later the dancers are back in an order they have already been in. Once we know when
+    // that happens, we only need to dance for the remainder of the cycle.
+    fn repeat_with_cycle_detection(dance: &DancePermutation, repetitions: u64) -> Vec<usize> {
+        let start: Vec<usize> = (0..dance.positions.len()).collect();
+        let cycle = brent(&start, |line| dance.apply(line));
+        let tail_length = cycle.tail_length as u64;
+        let mut current = start;
+
+        let steps = if repetitions < tail_length {
+            repetitions
+        } else {
+            tail_length + (repetitions - tail_length) % cycle.period as u64
+        };
+
+        for _ in 0..steps {
+            current = dance.apply(&current);
         }
-        let final_order = dance.final_order();
 
-        if count == 1 {
-            println!(
-                "Day 16 part 1. Final order of the dancers is {}.",
-                final_order
+        current
+    }
+
+    #[test]
+    fn cycle_detection_matches_power() {
+        let moves = parse_moves(&read_input("data/day_sixteen.txt")).expect("Invalid dance.");
+        let compiled = Dance::new().compile(&moves);
+        let start: Vec<usize> = (0..16).collect();
+
+        for &repetitions in [0, 1, 29, 30, 31, 1_000_000_000].iter() {
+            assert_eq!(
+                compiled.power(repetitions).apply(&start),
+                repeat_with_cycle_detection(&compiled, repetitions)
             );
         }
     }
 
-    println!(
-        "Day 16 part 2. Final order of the dancers is {}.",
-        dance.final_order()
-    );
+    #[test]
+    fn puzzle_example() {
+        let moves = parse_moves("s1,x3/4,pe/b").expect("Invalid dance.");
+        let mut dance = Dance::with_dancers(5);
+        dance.repeat(&moves, 2);
+
+        assert_eq!("ceadb", dance.to_string());
+    }
+
+    #[test]
+    fn moves_outside_the_line() {
+        let dance = Dance::with_dancers(5);
+
+        assert!(dance.validate(&parse_moves("s7,x4/0,pe/a").unwrap()).is_ok());
+        assert!(dance.validate(&parse_moves("s1,x7/1").unwrap()).is_err());
+        assert!(dance.validate(&parse_moves("pz/a").unwrap()).is_err());
+    }
 }
@@ -1,39 +1,121 @@
+// Parameters of the knot hash. The default values are the ones required by the puzzle, but
+// the examples use a smaller list, and changing them is useful to study variants of the hash.
+pub struct KnotHashConfig {
+    list_size: usize,
+    rounds: usize,
+    suffix: Vec<usize>,
+    block_size: usize,
+}
+
+impl KnotHashConfig {
+    // The dense hash XORs together blocks of |block_size| elements, so the list has to be
+    // split exactly into blocks. Each block is written as one byte, so the list cannot hold
+    // more than 256 values.
+    pub fn new(
+        list_size: usize,
+        rounds: usize,
+        suffix: Vec<usize>,
+        block_size: usize,
+    ) -> Result<KnotHashConfig, String> {
+        if list_size == 0 || block_size == 0 {
+            return Err(String::from("List size and block size must be positive."));
+        }
+
+        if list_size > 256 {
+            return Err(format!("List size {} does not fit in a byte.", list_size));
+        }
+
+        if !list_size.is_multiple_of(block_size) {
+            return Err(format!(
+                "List size {} is not a multiple of the block size {}.",
+                list_size,
+                block_size
+            ));
+        }
+
+        Ok(KnotHashConfig {
+            list_size,
+            rounds,
+            suffix,
+            block_size,
+        })
+    }
+}
+
+impl Default for KnotHashConfig {
+    fn default() -> KnotHashConfig {
+        KnotHashConfig::new(256, 64, vec![17, 31, 73, 47, 23], 16).expect("Invalid default.")
+    }
+}
+
 fn run_one_cycle(
-    sequence: &mut Vec<i32>,
-    lengths: &Vec<usize>,
+    sequence: &mut [i32],
+    lengths: &[usize],
     position: &mut usize,
     skip_size: &mut usize,
-) {
-    //let mut sequence = s.clone();
-    //let mut position = pos;
-    //let mut skip_size = ss;
+) -> Result<(), String> {
+    let len = sequence.len();
 
     for length in lengths.iter() {
-        let end_index: usize = (*position + *length - 1) % sequence.len();
+        if *length > len {
+            return Err(format!(
+                "Length {} is larger than the list size {}.",
+                length,
+                len
+            ));
+        }
 
+        // Adding |len| avoids an underflow when the length is 0 and we are at position 0.
         let mut i = *position;
-        let mut j = end_index;
+        let mut j = (*position + len + *length - 1) % len;
 
         for _ in 0..length / 2 {
-            let tmp = sequence[i];
-            sequence[i] = sequence[j];
-            sequence[j] = tmp;
-
-            i = (i + 1) % sequence.len();
-            if j == 0 {
-                j = sequence.len() - 1;
-            } else {
-                j -= 1;
-            }
+            sequence.swap(i, j);
+
+            i = (i + 1) % len;
+            j = (j + len - 1) % len;
         }
 
-        *position = (*position + *length + *skip_size) % sequence.len();
+        *position = (*position + *length + *skip_size) % len;
         *skip_size += 1;
     }
+
+    Ok(())
+}
+
+// Runs the configured number of rounds over a fresh list, keeping position and skip size
+// between rounds, and returns the resulting sparse hash. The suffix of the configuration is
+// not added to |lengths|.
+fn tie_knots(config: &KnotHashConfig, lengths: &[usize]) -> Result<Vec<i32>, String> {
+    let mut sequence: Vec<i32> = (0..config.list_size as i32).collect();
+    let mut position: usize = 0;
+    let mut skip_size: usize = 0;
+
+    for _ in 0..config.rounds {
+        run_one_cycle(&mut sequence, lengths, &mut position, &mut skip_size)?;
+    }
+
+    Ok(sequence)
+}
+
+// Computes the full knot hash of |input|, interpreted as a sequence of bytes, and returns it
+// as a hexadecimal string.
+pub fn knot_hash(config: &KnotHashConfig, input: &str) -> Result<String, String> {
+    let mut lengths: Vec<usize> = input.bytes().map(|x| x as usize).collect();
+    lengths.extend(config.suffix.iter());
+
+    let sequence = tie_knots(config, &lengths)?;
+    let mut dense_hash: String = String::new();
+
+    for block in sequence.chunks(config.block_size) {
+        let dense_hash_item: i32 = block.iter().fold(0, |acc, x| acc ^ x);
+        dense_hash.push_str(&format!("{:02x}", dense_hash_item));
+    }
+
+    Ok(dense_hash)
 }
 
 pub fn day_ten() {
-    let mut sequence: Vec<i32> = (0..256).collect();
     let lengths: Vec<usize> = [
         63,
         144,
@@ -53,52 +135,51 @@ pub fn day_ten() {
         24,
     ].to_vec();
 
-    let mut position: usize = 0;
-    let mut skip_size: usize = 0;
+    // Part 1 only runs one round. The example in the puzzle uses a list of 5 elements.
+    let example_config = KnotHashConfig::new(5, 1, Vec::new(), 1).expect("Invalid config.");
+    let example = tie_knots(&example_config, &[3, 4, 1, 5]).expect("Invalid lengths.");
+    println!(
+        "Day 10 part 1. Example result is {}.",
+        example[0] * example[1]
+    );
 
-    run_one_cycle(&mut sequence, &lengths, &mut position, &mut skip_size);
+    let config = KnotHashConfig::new(256, 1, Vec::new(), 16).expect("Invalid config.");
+    let sequence = tie_knots(&config, &lengths).expect("Invalid lengths.");
 
     println!("Day 10 part 1. Result is {}.", sequence[0] * sequence[1]);
 }
 
 pub fn day_ten_part_2() {
-    let mut sequence: Vec<i32> = (0..256).collect();
-
-    let input = "63,144,180,149,1,255,167,84,125,65,188,0,2,254,229,24"
-        .as_bytes()
-        .to_vec();
-    let mut lengths: Vec<usize> = input.iter().map(|x| *x as usize).collect();
-    lengths.push(17);
-    lengths.push(31);
-    lengths.push(73);
-    lengths.push(47);
-    lengths.push(23);
+    let input = "63,144,180,149,1,255,167,84,125,65,188,0,2,254,229,24";
+    let dense_hash = knot_hash(&KnotHashConfig::default(), input).expect("Invalid input.");
 
-    let mut position: usize = 0;
-    let mut skip_size: usize = 0;
-
-    for _ in 0..64 {
-        run_one_cycle(&mut sequence, &lengths, &mut position, &mut skip_size);
-    }
+    println!("Day 10 part 2. Result is {}.", dense_hash);
+}
 
-    let mut dense_hash: String = String::new();
-    let mut block_start = 0;
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    while block_start < sequence.len() {
-        let mut dense_hash_item: i32 = sequence[block_start];
+    #[test]
+    fn puzzle_examples() {
+        let config = KnotHashConfig::new(5, 1, Vec::new(), 1).unwrap();
+        assert_eq!(tie_knots(&config, &[3, 4, 1, 5]).unwrap(), vec![3, 4, 2, 1, 0]);
 
-        for i in block_start + 1..block_start + 16 {
-            dense_hash_item = dense_hash_item ^ sequence[i];
-        }
-
-        let mut tmp = format!("{:x}", dense_hash_item);
-        if tmp.len() == 1 {
-            tmp.insert(0, '0');
-        }
+        let config = KnotHashConfig::default();
+        assert_eq!(knot_hash(&config, "").unwrap(), "a2582a3a0e66e6e86e3812dcb672a272");
+        assert_eq!(knot_hash(&config, "AoC 2017").unwrap(), "33efeb34ea91902bb2f59c9920caa6cd");
+    }
 
-        dense_hash.push_str(&tmp);
-        block_start += 16;
+    #[test]
+    fn invalid_configs() {
+        assert!(KnotHashConfig::new(0, 1, Vec::new(), 1).is_err());
+        assert!(KnotHashConfig::new(10, 1, Vec::new(), 4).is_err());
+        assert!(KnotHashConfig::new(512, 1, Vec::new(), 16).is_err());
     }
 
-    println!("Day 10 part 2. Result is {}.", dense_hash);
+    #[test]
+    fn length_larger_than_list() {
+        let config = KnotHashConfig::new(5, 1, Vec::new(), 1).unwrap();
+        assert!(tie_knots(&config, &[3, 6]).is_err());
+    }
 }
@@ -1,3 +1,4 @@
+use std::collections::BTreeMap;
 use utils::read_input;
 
 fn parse_firewall(input: &String) -> Vec<u32> {
@@ -24,14 +25,184 @@ fn parse_firewall(input: &String) -> Vec<u32> {
     firewall
 }
 
-fn compute_scanner_position(range: u32, picosecond: u32, delay: u32) -> u32 {
-    let mut pos = (picosecond + delay) % (range * 2 - 2);
+// The scanner of a layer goes back and forth, so it is back at the top every
+// 2 * (range - 1) picoseconds.
+fn scanner_period(range: u32) -> u64 {
+    assert!(range > 1, "A scanner with range {} never moves.", range);
+    2 * (u64::from(range) - 1)
+}
+
+fn compute_scanner_position(range: u32, picosecond: u32, delay: u64) -> u32 {
+    // A scanner with a single row never leaves it.
+    if range <= 1 {
+        return 0;
+    }
+
+    let period = scanner_period(range);
+    let pos = (u64::from(picosecond) + delay) % period;
+
+    // Past the bottom, the scanner is on its way back up.
+    if pos >= u64::from(range) {
+        (period - pos) as u32
+    } else {
+        pos as u32
+    }
+}
+
+fn gcd(a: u64, b: u64) -> u64 {
+    if b == 0 { a } else { gcd(b, a % b) }
+}
+
+// Above this many candidate residues, the sieve stops merging layers and checks the remaining
+// ones on each candidate delay instead.
+const MAX_SIEVE_RESIDUES: u64 = 1 << 20;
+
+// The packet is caught at layer |depth| when delay + depth is a multiple of the period of its
+// scanner, so each layer forbids one residue of the delay modulo that period. Layers with the
+// same period are grouped first. Then, starting from the smallest periods, the allowed residues
+// are combined modulo the LCM of the periods seen so far, as long as there are not too many of
+// them. Finally the candidates are enumerated in increasing order and checked against the
+// layers left out of the sieve.
+//
+// Returns None if every delay gets the packet caught.
+fn find_minimum_delay(firewall: &[u32]) -> Option<u64> {
+    let mut forbidden: BTreeMap<u64, Vec<bool>> = BTreeMap::new();
+
+    for (depth, &range) in firewall.iter().enumerate() {
+        match range {
+            0 => continue,
+            // The scanner never leaves the top, so there is no way through.
+            1 => return None,
+            _ => {}
+        }
+
+        let period = scanner_period(range);
+        let residues = forbidden.entry(period).or_insert_with(
+            || vec![false; period as usize],
+        );
+        residues[((period - depth as u64 % period) % period) as usize] = true;
+    }
+
+    let mut layers: Vec<(u64, Vec<bool>)> = forbidden.into_iter().collect();
+    let mut modulus: u64 = 1;
+    let mut residues: Vec<u64> = vec![0];
+
+    while let Some(&(period, _)) = layers.first() {
+        let lcm = modulus / gcd(modulus, period) * period;
+        if residues.len() as u64 * (lcm / modulus) > MAX_SIEVE_RESIDUES {
+            break;
+        }
+
+        let (_, period_forbidden) = layers.remove(0);
+        let mut combined: Vec<u64> = Vec::new();
+
+        for base in (0..lcm).step_by(modulus as usize) {
+            for &residue in residues.iter() {
+                if !period_forbidden[((base + residue) % period) as usize] {
+                    combined.push(base + residue);
+                }
+            }
+        }
+
+        if combined.is_empty() {
+            return None;
+        }
+
+        modulus = lcm;
+        residues = combined;
+    }
+
+    // The pattern of allowed delays repeats with the LCM of all the periods: if nothing is
+    // found below it, there is no solution. The LCM can get huge, in which case we just keep
+    // searching.
+    let limit = layers.iter().try_fold(modulus, |m, &(period, _)| {
+        (m / gcd(m, period)).checked_mul(period)
+    });
+
+    let mut base: u64 = 0;
+    while limit.is_none_or(|l| base < l) {
+        for &residue in residues.iter() {
+            let delay = base + residue;
+            if layers.iter().all(|&(period, ref f)| !f[(delay % period) as usize]) {
+                return Some(delay);
+            }
+        }
+
+        base = base.checked_add(modulus)?;
+    }
+
+    None
+}
+
+// Draws the firewall as in the puzzle description: one column per layer, with the scanner
+// shown as "S" and empty layers as "...". The packet, when it is inside the firewall, is in
+// parentheses on the top row of its layer.
+fn render_firewall(firewall: &[u32], picosecond: u32, delay: u64, packet_layer: Option<usize>) {
+    let header: Vec<String> = (0..firewall.len()).map(|i| format!("{:^3}", i)).collect();
+    println!("{}", header.join(" ").trim_end());
+
+    let depth = firewall.iter().cloned().max().unwrap_or(0).max(1);
+
+    for row in 0..depth {
+        let cells: Vec<String> = firewall
+            .iter()
+            .enumerate()
+            .map(|(layer, &range)| {
+                let (left, content, right) = if range == 0 {
+                    if row == 0 { (".", ".", ".") } else { (" ", " ", " ") }
+                } else if row >= range {
+                    (" ", " ", " ")
+                } else if compute_scanner_position(range, picosecond, delay) == row {
+                    ("[", "S", "]")
+                } else {
+                    ("[", " ", "]")
+                };
+
+                if row == 0 && Some(layer) == packet_layer {
+                    format!("({})", content)
+                } else {
+                    format!("{}{}{}", left, content, right)
+                }
+            })
+            .collect();
+
+        println!("{}", cells.join(" ").trim_end());
+    }
+}
+
+// Shows the trip of the packet through the firewall, picosecond by picosecond, for a given
+// delay. Usage: "cargo run 13 timeline <delay> [input file]".
+pub fn day_thirteen_timeline(args: &[String]) {
+    let delay: u64 = args.first()
+        .expect("Usage: \"cargo run 13 timeline <delay> [input file]\"")
+        .parse()
+        .expect("Expected a number.");
+    let path = args.get(1).map_or("data/day_thirteen.txt", |p| p.as_str());
+    let firewall: Vec<u32> = parse_firewall(&read_input(path));
+    let mut severity = 0;
+    let mut caught_count = 0;
+
+    for packet_layer in 0..firewall.len() as u32 {
+        let scanner_range: u32 = firewall[packet_layer as usize];
+
+        println!("Picosecond {}:", delay + u64::from(packet_layer));
+        render_firewall(&firewall, packet_layer, delay, Some(packet_layer as usize));
+
+        if scanner_range != 0 && compute_scanner_position(scanner_range, packet_layer, delay) == 0 {
+            severity += scanner_range * packet_layer;
+            caught_count += 1;
+            println!("Caught at layer {}!", packet_layer);
+        }
 
-    if pos >= range {
-        pos = range - (pos - range) - 1;
+        println!();
     }
 
-    pos
+    println!(
+        "Day 13. With a delay of {}, the packet is caught {} times, with severity {}.",
+        delay,
+        caught_count,
+        severity
+    );
 }
 
 pub fn day_thirteen() {
@@ -53,30 +224,29 @@ pub fn day_thirteen() {
         }
     }
 
-    let mut pico_delay: u32 = 1;
+    let pico_delay = find_minimum_delay(&firewall).expect("The packet is always caught.");
 
-    loop {
-        let mut caught = false;
-
-        for packet_layer in 0..firewall.len() as u32 {
-            let scanner_range: u32 = firewall[packet_layer as usize];
-            if scanner_range == 0 {
-                continue;
-            }
-
-            if compute_scanner_position(scanner_range, packet_layer, pico_delay) == 0 {
-                caught = true;
-                break;
-            }
-        }
-
-        if !caught {
-            break;
-        }
+    println!("Day 13 part 1. Severity of the path is {}.", severity);
+    println!("Day 13 part 2. Minimum delay is {}.", pico_delay);
+}
 
-        pico_delay += 1;
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scanner_goes_back_up() {
+        // A scanner with range 4 goes through rows 0, 1, 2, 3, 2, 1 and starts again.
+        let rows: Vec<u32> = (0..8).map(|t| compute_scanner_position(4, t, 0)).collect();
+        assert_eq!(vec![0, 1, 2, 3, 2, 1, 0, 1], rows);
+        assert_eq!(1, compute_scanner_position(3, 3, 0));
+        assert_eq!(2, compute_scanner_position(4, 0, 4));
+        assert_eq!(0, compute_scanner_position(1, 5, 3));
     }
 
-    println!("Day 13 part 1. Severity of the path is {}.", severity);
-    println!("Day 13 part 2. Minimum delay is {}.", pico_delay);
+    #[test]
+    fn puzzle_example() {
+        let firewall = parse_firewall(&String::from("0: 3\n1: 2\n4: 4\n6: 4\n"));
+        assert_eq!(Some(10), find_minimum_delay(&firewall));
+    }
 }
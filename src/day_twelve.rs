@@ -1,10 +1,11 @@
-use utils::read_input;
+use utils::{distinct_colour, read_input, write_output};
 use std::collections::HashMap;
-use std::collections::HashSet;
+use union_find::UnionFind;
+use graph::{asymmetric_edges, find_cuts, nodes, shortest_path, AdjacencyList};
 
 // Builds the adjacency list of the programs from the input.
-fn build_adjacency_list(contents: &str) -> HashMap<i32, Vec<i32>> {
-    let mut adjacency_list: HashMap<i32, Vec<i32>> = HashMap::new();
+fn build_adjacency_list(contents: &str) -> AdjacencyList {
+    let mut adjacency_list: AdjacencyList = HashMap::new();
 
     for line in contents.split('\n') {
         if line.is_empty() {
@@ -33,64 +34,284 @@ fn build_adjacency_list(contents: &str) -> HashMap<i32, Vec<i32>> {
     adjacency_list
 }
 
-pub fn day_twelve() {
-    let contents = read_input("data/day_twelve.txt");
-    let adjacency_list = build_adjacency_list(&contents);
+// The groups of programs that can communicate with each other, i.e. the connected components
+// of the graph of the pipes.
+struct ProgramGroups {
+    ids: Vec<i32>,
+    indices: HashMap<i32, usize>,
+    sets: UnionFind,
+    // For the representative of each group, the smallest program id in the group.
+    smallest_ids: Vec<i32>,
+}
 
-    let mut all_programs: Vec<&i32> = adjacency_list.keys().collect();
-    let mut count_groups = 0;
+impl ProgramGroups {
+    fn from_adjacency_list(adjacency_list: &AdjacencyList) -> ProgramGroups {
+        let mut ids: Vec<i32> = adjacency_list.keys().cloned().collect();
+        ids.sort();
 
-    // This solves part 2 and implicitly part 1 with a trick.
-    while !all_programs.is_empty() {
-        let mut queue: Vec<i32> = Vec::new();
-        let mut seen: HashSet<i32> = HashSet::new();
+        let mut groups = ProgramGroups {
+            ids: Vec::new(),
+            indices: HashMap::new(),
+            sets: UnionFind::new(),
+            smallest_ids: Vec::new(),
+        };
 
-        let first_element: &i32 = all_programs.pop().expect("Impossible!");
-        // Used because remove_item() is still in the experimental builds. This is
-        // a viable workaround.
-        all_programs.retain(|&x| x != first_element);
+        for id in ids {
+            groups.add_program(id);
+        }
 
-        // Each new "first element" starts a new group, so we increment the counter.
-        queue.push(*first_element);
-        count_groups += 1;
+        for (program, neighbours) in adjacency_list {
+            let index = groups.indices[program];
 
-        let mut group_size = 1;
-        let mut group_contains_0 = false;
+            for neighbour in neighbours {
+                let neighbour_index = groups.add_program(*neighbour);
+                groups.sets.union(index, neighbour_index);
+            }
+        }
 
-        // Here we build the entire group of programs reachable from |first_element|, and
-        // we count its members. If we find program 0 among the members, we will print
-        // the number of members at the end.
-        while !queue.is_empty() {
-            let next = queue.pop().expect(
-                "Queue appears to be empty when it should not be.",
-            );
-            seen.insert(next);
+        groups.smallest_ids = groups.ids.clone();
+        for index in 0..groups.ids.len() {
+            let root = groups.sets.find(index);
+            groups.smallest_ids[root] = groups.smallest_ids[root].min(groups.ids[index]);
+        }
 
-            if next == 0 {
-                group_contains_0 = true;
-            }
+        groups
+    }
+
+    // Returns the index of |id| in the disjoint sets, adding it if it is not there yet.
+    fn add_program(&mut self, id: i32) -> usize {
+        if let Some(index) = self.indices.get(&id) {
+            return *index;
+        }
+
+        let index = self.sets.make_set();
+        self.ids.push(id);
+        self.indices.insert(id, index);
+        index
+    }
+
+    // Returns the program representing the group of |id|, which is the one with the smallest
+    // id: two programs are in the same group if and only if they have the same representative.
+    fn group_of(&mut self, id: i32) -> Option<i32> {
+        let index = *self.indices.get(&id)?;
+        let root = self.sets.find(index);
+        Some(self.smallest_ids[root])
+    }
+
+    fn group_size(&mut self, id: i32) -> Option<usize> {
+        let index = *self.indices.get(&id)?;
+        Some(self.sets.size(index))
+    }
+
+    fn num_groups(&self) -> usize {
+        self.sets.num_sets()
+    }
+
+    fn same_group(&mut self, a: i32, b: i32) -> bool {
+        match (self.group_of(a), self.group_of(b)) {
+            (Some(group_a), Some(group_b)) => group_a == group_b,
+            _ => false,
+        }
+    }
+}
 
-            match adjacency_list.get(&next) {
-                Some(neighbours) => {
-                    for neighbour in neighbours {
-                        if !seen.contains(neighbour) {
-                            all_programs.retain(|&x| x != neighbour);
-                            queue.push(*neighbour);
-                            group_size += 1;
-                        }
-                    }
-                }
-                None => (),
+// Answers questions about the group of a program. Usage: "cargo run 12 query <id> [<id>]".
+// With two programs, it also tells whether they are in the same group.
+pub fn day_twelve_query(args: &[String]) {
+    let ids: Vec<i32> = args.iter()
+        .map(|a| a.parse().expect("Expected a program id."))
+        .collect();
+    let contents = read_input("data/day_twelve.txt");
+    let mut groups = ProgramGroups::from_adjacency_list(&build_adjacency_list(&contents));
+
+    if ids.is_empty() || ids.len() > 2 {
+        panic!("Usage: \"cargo run 12 query <id> [<id>]\"");
+    }
+
+    for id in ids.iter() {
+        match (groups.group_of(*id), groups.group_size(*id)) {
+            (Some(group), Some(size)) => {
+                println!(
+                    "Day 12. Program {} is in the group of program {}, with {} programs.",
+                    id,
+                    group,
+                    size
+                )
             }
+            _ => println!("Day 12. There is no program {}.", id),
         }
+    }
+
+    if ids.len() == 2 {
+        println!(
+            "Day 12. Programs {} and {} in the same group: {}.",
+            ids[0],
+            ids[1],
+            groups.same_group(ids[0], ids[1])
+        );
+    }
+}
+
+// Assigns a colour to each program, so that programs in the same group share it.
+fn group_colours(adjacency_list: &AdjacencyList) -> Vec<(i32, String)> {
+    let mut groups = ProgramGroups::from_adjacency_list(adjacency_list);
+    let mut group_indices: HashMap<i32, u32> = HashMap::new();
+    let mut result: Vec<(i32, String)> = Vec::new();
+
+    for program in nodes(adjacency_list) {
+        let group = groups.group_of(program).expect("Unknown program.");
+        let next_index = group_indices.len() as u32;
+        let (r, g, b) = distinct_colour(*group_indices.entry(group).or_insert(next_index));
+
+        result.push((program, format!("#{:02x}{:02x}{:02x}", r, g, b)));
+    }
+
+    result
+}
+
+// Returns each pipe once, as a pair of programs with the smallest first.
+fn pipes(adjacency_list: &AdjacencyList) -> Vec<(i32, i32)> {
+    let mut result: Vec<(i32, i32)> = Vec::new();
 
-        if group_contains_0 {
+    for program in nodes(adjacency_list) {
+        for &neighbour in adjacency_list.get(&program).map_or(&[][..], |n| n.as_slice()) {
+            result.push((program.min(neighbour), program.max(neighbour)));
+        }
+    }
+
+    result.sort();
+    result.dedup();
+    result
+}
+
+fn to_dot(adjacency_list: &AdjacencyList) -> String {
+    let mut dot = String::from("graph pipes {\n    node [style=filled];\n");
+
+    for (program, colour) in group_colours(adjacency_list) {
+        dot.push_str(&format!("    {} [fillcolor=\"{}\"];\n", program, colour));
+    }
+
+    for (a, b) in pipes(adjacency_list) {
+        dot.push_str(&format!("    {} -- {};\n", a, b));
+    }
+
+    dot.push_str("}\n");
+    dot
+}
+
+fn to_graphml(adjacency_list: &AdjacencyList) -> String {
+    let mut groups = ProgramGroups::from_adjacency_list(adjacency_list);
+    let mut graphml = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n  \
+         <key id=\"group\" for=\"node\" attr.name=\"group\" attr.type=\"int\"/>\n  \
+         <key id=\"color\" for=\"node\" attr.name=\"color\" attr.type=\"string\"/>\n  \
+         <graph id=\"pipes\" edgedefault=\"undirected\">\n",
+    );
+
+    for (program, colour) in group_colours(adjacency_list) {
+        graphml.push_str(&format!(
+            "    <node id=\"n{}\"><data key=\"group\">{}</data>\
+             <data key=\"color\">{}</data></node>\n",
+            program,
+            groups.group_of(program).expect("Unknown program."),
+            colour
+        ));
+    }
+
+    for (a, b) in pipes(adjacency_list) {
+        graphml.push_str(&format!(
+            "    <edge source=\"n{}\" target=\"n{}\"/>\n",
+            a,
+            b
+        ));
+    }
+
+    graphml.push_str("  </graph>\n</graphml>\n");
+    graphml
+}
+
+// Exports the network of pipes, with each group in a different colour. Usage:
+//   cargo run 12 export <dot|graphml> <output file>
+pub fn day_twelve_export(args: &[String]) {
+    if args.len() != 2 {
+        panic!("Usage: \"cargo run 12 export <dot|graphml> <output file>\"");
+    }
+
+    let adjacency_list = build_adjacency_list(&read_input("data/day_twelve.txt"));
+    let contents = match args[0].as_str() {
+        "dot" => to_dot(&adjacency_list),
+        "graphml" => to_graphml(&adjacency_list),
+        format => panic!("Unknown format {}.", format),
+    };
+
+    write_output(&args[1], &contents);
+
+    println!("Day 12. Network written to {}.", args[1]);
+}
+
+// Finds a shortest chain of pipes between two programs. Usage: "cargo run 12 path <id> <id>".
+pub fn day_twelve_path(args: &[String]) {
+    if args.len() != 2 {
+        panic!("Usage: \"cargo run 12 path <id> <id>\"");
+    }
+
+    let from: i32 = args[0].parse().expect("Expected a program id.");
+    let to: i32 = args[1].parse().expect("Expected a program id.");
+    let adjacency_list = build_adjacency_list(&read_input("data/day_twelve.txt"));
+
+    match shortest_path(&adjacency_list, from, to) {
+        Some(path) => {
+            let programs: Vec<String> = path.iter().map(|p| p.to_string()).collect();
             println!(
-                "Day 12 part 1. Group with program 0 contains {} programs.",
-                group_size
+                "Day 12. Shortest path has {} pipes: {}.",
+                path.len() - 1,
+                programs.join(" <-> ")
             );
         }
+        None => println!("Day 12. Program {} cannot reach program {}.", from, to),
     }
+}
+
+// Lists the programs and pipes whose removal would split a group. Usage: "cargo run 12 cuts".
+pub fn day_twelve_cuts(_args: &[String]) {
+    let adjacency_list = build_adjacency_list(&read_input("data/day_twelve.txt"));
+    let cuts = find_cuts(&adjacency_list);
+    let points: Vec<String> = cuts.articulation_points
+        .iter()
+        .map(|p| p.to_string())
+        .collect();
+    let bridges: Vec<String> = cuts.bridges
+        .iter()
+        .map(|&(a, b)| format!("{} <-> {}", a, b))
+        .collect();
+
+    println!(
+        "Day 12. {} articulation points: {}.",
+        points.len(),
+        points.join(", ")
+    );
+    println!("Day 12. {} bridges: {}.", bridges.len(), bridges.join(", "));
+}
+
+pub fn day_twelve() {
+    let contents = read_input("data/day_twelve.txt");
+    let adjacency_list = build_adjacency_list(&contents);
+
+    // Pipes are bidirectional, so each program should list all the programs listing it.
+    for (a, b) in asymmetric_edges(&adjacency_list) {
+        println!(
+            "Day 12. Warning: program {} is connected to {}, but not the other way around.",
+            a,
+            b
+        );
+    }
+
+    let mut groups = ProgramGroups::from_adjacency_list(&adjacency_list);
 
-    println!("Day 12 part 2. There are {} groups.", count_groups);
+    println!(
+        "Day 12 part 1. Group with program 0 contains {} programs.",
+        groups.group_size(0).expect("There is no program 0.")
+    );
+    println!("Day 12 part 2. There are {} groups.", groups.num_groups());
 }
@@ -1,5 +1,5 @@
-use utils::read_input;
-use std::collections::HashSet;
+use utils::{distinct_colour, read_input, write_output};
+use std::collections::{HashMap, HashSet};
 
 #[derive(Hash, Clone)]
 struct Vector {
@@ -28,6 +28,7 @@ impl Vector {
         self.z += other.z;
     }
 
+    #[cfg(test)]
     fn distance(&self) -> u64 {
         (self.x.abs() + self.y.abs() + self.z.abs()) as u64
     }
@@ -55,6 +56,7 @@ impl Particle {
         self.pos.sum(&self.vel);
     }
 
+    #[cfg(test)]
     fn distance(&self) -> u64 {
         self.pos.distance()
     }
@@ -121,150 +123,654 @@ fn parse_swarm(contents: &str) -> Vec<Particle> {
     swarm
 }
 
-fn solve_quadratic(a: f64, b: f64, c: f64) -> Option<Vec<f64>> {
-    let mut solutions: Vec<f64> = Vec::new();
+// The ticks at which two particles have the same coordinate along one axis.
+enum AxisMeetings {
+    // Every tick: they have the same position, velocity and acceleration along the axis.
+    Always,
+    Ticks(Vec<u64>),
+}
 
-    if a == 0.0 {
-        if b == 0.0 {
-            if c == 0.0 {
-                use std::f64::NAN;
-                solutions.push(NAN);
+// Finds the ticks t >= 0 at which the difference between two coordinates is 0, given the
+// differences of their positions, velocities and accelerations. After t ticks a coordinate
+// is at
+//   r(t) = r(0) + t*v(0) + G(t)*a(0)
+// where G(t) is the Gaussian sum, i.e. 0+1+...+t = [t(t+1)]/2. Multiplying by 2 to stay with
+// integers, the difference is 0 when
+//   da*t^2 + (2*dv + da)*t + 2*dp = 0
+// and we only keep the roots that are non-negative integers.
+fn meeting_ticks(dp: i64, dv: i64, da: i64) -> AxisMeetings {
+    let a = i128::from(da);
+    let b = 2 * i128::from(dv) + i128::from(da);
+    let c = 2 * i128::from(dp);
+    let mut roots: Vec<(i128, i128)> = Vec::new();
+
+    if a == 0 {
+        if b == 0 {
+            return if c == 0 {
+                AxisMeetings::Always
             } else {
-                return None;
-            }
-        } else {
-            solutions.push(-c / b);
+                AxisMeetings::Ticks(Vec::new())
+            };
         }
+
+        roots.push((-c, b));
     } else {
-        let square = b.powf(2.0) - 4.0 * a * c;
-
-        if square == 0.0 {
-            solutions.push(-b / (2.0 * a));
-        } else if square < 0.0 {
-            return None;
-        } else {
-            solutions.push((-b + square.sqrt()) / (2.0 * a));
-            solutions.push((-b - square.sqrt()) / (2.0 * a));
+        let discriminant = b * b - 4 * a * c;
+        if discriminant < 0 {
+            return AxisMeetings::Ticks(Vec::new());
         }
+
+        // Integer roots need the square root of the discriminant to be an integer too.
+        let root = discriminant.isqrt();
+        if root * root != discriminant {
+            return AxisMeetings::Ticks(Vec::new());
+        }
+
+        roots.push((-b + root, 2 * a));
+        roots.push((-b - root, 2 * a));
     }
 
-    Some(solutions)
+    let mut ticks: Vec<u64> = roots
+        .into_iter()
+        .filter(|&(numerator, denominator)| numerator % denominator == 0)
+        .map(|(numerator, denominator)| numerator / denominator)
+        .filter(|&t| t >= 0)
+        .map(|t| t as u64)
+        .collect();
+    ticks.sort();
+    ticks.dedup();
+
+    AxisMeetings::Ticks(ticks)
 }
 
-fn find_collision(p1: &Particle, p2: &Particle) -> Option<Vec<f64>> {
-    // The position along any axis of a particle at time t is given by:
-    //   r(t) = r(0) + t*v(0) + G(t)*a(0)
-    // where G(t) is the Gaussian sum, i.e. 0+1+...+t = [t(t+1)]/2.
-    //
-    // So we get a quadratic equation in the form a*t^2 + b*t + c. Here we derive the
-    // coefficients for all three equations, one for each dimension.
-    let cx: f64 = (p1.pos.x - p2.pos.x) as f64;
-    let bx: f64 = (p1.vel.x as f64 - p2.vel.x as f64 + (p1.acc.x as f64 / 2.0) -
-                       (p2.acc.x as f64 / 2.0)) as f64;
-    let ax: f64 = (p1.acc.x - p2.acc.x) as f64 / 2.0;
-
-    let cy: f64 = (p1.pos.y - p2.pos.y) as f64;
-    let by: f64 = (p1.vel.y as f64 - p2.vel.y as f64 + (p1.acc.y as f64 / 2.0) -
-                       (p2.acc.y as f64 / 2.0)) as f64;
-    let ay: f64 = (p1.acc.y - p2.acc.y) as f64 / 2.0;
-
-    let cz: f64 = (p1.pos.z - p2.pos.z) as f64;
-    let bz: f64 = (p1.vel.z as f64 - p2.vel.z as f64 + (p1.acc.z as f64 / 2.0) -
-                       (p2.acc.z as f64 / 2.0)) as f64;
-    let az: f64 = (p1.acc.z - p2.acc.z) as f64 / 2.0;
-
-    let maybe_sol_x = solve_quadratic(ax, bx, cx);
-    let maybe_sol_y = solve_quadratic(ay, by, cy);
-    let maybe_sol_z = solve_quadratic(az, bz, cz);
-
-    // We have to find at least one solution for each dimension, and there has to be at least one
-    // solution in common between the three. A special case is when the vector with the solution
-    // contains one element, NAN: this means the two particles follow the exact same trajectory
-    // in that dimension so the equation has infinite solutions.
-    if maybe_sol_x.is_none() || maybe_sol_y.is_none() || maybe_sol_z.is_none() {
-        return None;
-    }
-
-    let sol_x = maybe_sol_x.unwrap();
-    let sol_y = maybe_sol_y.unwrap();
-    let sol_z = maybe_sol_z.unwrap();
-    let mut all_solutions: Vec<f64> = Vec::new();
-
-    if !sol_x[0].is_nan() {
-        for x in sol_x {
-            if (sol_y.contains(&x) || sol_y[0].is_nan()) &&
-                (sol_z.contains(&x) || sol_z[0].is_nan())
-            {
-                all_solutions.push(x);
+// Returns the first tick at which the two particles are at the same position, if any.
+fn find_collision(p1: &Particle, p2: &Particle) -> Option<u64> {
+    let axes = [
+        meeting_ticks(p1.pos.x - p2.pos.x, p1.vel.x - p2.vel.x, p1.acc.x - p2.acc.x),
+        meeting_ticks(p1.pos.y - p2.pos.y, p1.vel.y - p2.vel.y, p1.acc.y - p2.acc.y),
+        meeting_ticks(p1.pos.z - p2.pos.z, p1.vel.z - p2.vel.z, p1.acc.z - p2.acc.z),
+    ];
+
+    // None means that so far, any tick would do.
+    let mut common_ticks: Option<Vec<u64>> = None;
+
+    for axis in axes.iter() {
+        if let AxisMeetings::Ticks(ref ticks) = *axis {
+            common_ticks = Some(match common_ticks {
+                None => ticks.clone(),
+                Some(common) => common.into_iter().filter(|t| ticks.contains(t)).collect(),
+            });
+        }
+    }
+
+    match common_ticks {
+        // The two particles are the same: they collide straight away.
+        None => Some(0),
+        Some(common) => common.first().cloned(),
+    }
+}
+
+// Resolves the collisions in the order they happen: a particle destroyed at some tick cannot
+// collide with anything afterwards.
+fn resolve_collisions(swarm: &[Particle]) -> SimulationResult {
+    let mut collisions: Vec<(u64, usize, usize)> = Vec::new();
+
+    for i in 0..swarm.len() {
+        for j in i + 1..swarm.len() {
+            if let Some(tick) = find_collision(&swarm[i], &swarm[j]) {
+                collisions.push((tick, i, j));
             }
         }
-    } else if !sol_y[0].is_nan() {
-        for y in sol_y {
-            if sol_z.contains(&y) || sol_z[0].is_nan() {
-                all_solutions.push(y);
+    }
+
+    collisions.sort();
+    let mut destroyed: Vec<bool> = vec![false; swarm.len()];
+    let mut last_collision: Option<u64> = None;
+
+    for same_tick in collisions.chunk_by(|c1, c2| c1.0 == c2.0) {
+        let colliding: Vec<(usize, usize)> = same_tick
+            .iter()
+            .filter(|&&(_, i, j)| !destroyed[i] && !destroyed[j])
+            .map(|&(_, i, j)| (i, j))
+            .collect();
+
+        if !colliding.is_empty() {
+            last_collision = Some(same_tick[0].0);
+        }
+
+        for (i, j) in colliding {
+            destroyed[i] = true;
+            destroyed[j] = true;
+        }
+    }
+
+    SimulationResult {
+        last_collision,
+        survivors: (0..swarm.len()).filter(|&i| !destroyed[i]).collect(),
+    }
+}
+
+// The sign a coordinate ends up with: that of the acceleration if there is one, otherwise
+// that of the velocity, otherwise that of the position.
+fn eventual_sign(p: i64, v: i64, a: i64) -> i64 {
+    if a != 0 {
+        a.signum()
+    } else if v != 0 {
+        v.signum()
+    } else {
+        p.signum()
+    }
+}
+
+// After t ticks a coordinate is at a/2*t^2 + (v + a/2)*t + p. Once t is large enough, every
+// coordinate has its eventual sign s, so the Manhattan distance to the origin is
+//   2 * distance(t) = k2*t^2 + k1*t + 2*k0
+// with k2 = sum(s*a) = sum(|a|), k1 = sum(s*(2*v + a)) and k0 = sum(s*p). In other words the
+// magnitude of the acceleration, then the velocity projected onto it, then the position.
+// Comparing these coefficients in order compares the distances in the long run, and
+// particles with the same coefficients stay at the same distance forever.
+fn long_run_distance(particle: &Particle) -> (i64, i64, i64) {
+    let axes = [
+        (particle.pos.x, particle.vel.x, particle.acc.x),
+        (particle.pos.y, particle.vel.y, particle.acc.y),
+        (particle.pos.z, particle.vel.z, particle.acc.z),
+    ];
+    let (mut k2, mut k1, mut k0) = (0, 0, 0);
+
+    for &(p, v, a) in axes.iter() {
+        let sign = eventual_sign(p, v, a);
+        k2 += sign * a;
+        k1 += sign * (2 * v + a);
+        k0 += sign * p;
+    }
+
+    (k2, k1, k0)
+}
+
+// Orders the particles from the closest to the origin in the long run to the furthest.
+// Particles which end up at the same distance forever are grouped together, by index.
+fn rank_by_long_run_distance(swarm: &[Particle]) -> Vec<Vec<usize>> {
+    let mut keys: Vec<((i64, i64, i64), usize)> = swarm
+        .iter()
+        .enumerate()
+        .map(|(i, particle)| (long_run_distance(particle), i))
+        .collect();
+    keys.sort();
+
+    keys.chunk_by(|k1, k2| k1.0 == k2.0)
+        .map(|group| group.iter().map(|&(_, i)| i).collect())
+        .collect()
+}
+
+// Returns true if the distance between two particles along one axis, given the differences of
+// their positions, velocities and accelerations, is not 0 and can only grow from now on. If
+// the position difference is positive, and the velocity and acceleration differences are not
+// negative, then after one tick the velocity difference is still not negative and the position
+// difference is still positive. By induction this holds forever; same with all signs flipped.
+fn moving_apart(dp: i64, dv: i64, da: i64) -> bool {
+    (dp > 0 && dv >= 0 && da >= 0) || (dp < 0 && dv <= 0 && da <= 0)
+}
+
+// Returns true if the two particles can never be at the same position again, because they
+// are moving apart along at least one axis. Any two particles at different positions end
+// up like this: along an axis where their accelerations differ, the velocity and then the
+// position differences eventually take the sign of the acceleration difference. Without any
+// acceleration difference the same goes with velocities, and without either the positions
+// differ along some axis and stay so.
+fn can_never_meet(p1: &Particle, p2: &Particle) -> bool {
+    moving_apart(p1.pos.x - p2.pos.x, p1.vel.x - p2.vel.x, p1.acc.x - p2.acc.x) ||
+        moving_apart(p1.pos.y - p2.pos.y, p1.vel.y - p2.vel.y, p1.acc.y - p2.acc.y) ||
+        moving_apart(p1.pos.z - p2.pos.z, p1.vel.z - p2.vel.z, p1.acc.z - p2.acc.z)
+}
+
+// The collisions of a swarm, once no more can happen.
+#[derive(Debug, PartialEq)]
+struct SimulationResult {
+    // None if no particles ever collide.
+    last_collision: Option<u64>,
+    // Indices of the particles left, in the original swarm.
+    survivors: Vec<usize>,
+}
+
+// A swarm of particles, moving tick by tick and destroyed when they collide.
+struct Swarm {
+    particles: Vec<Particle>,
+    ids: Vec<usize>,
+    tick: u64,
+}
+
+impl Swarm {
+    fn new(particles: Vec<Particle>) -> Swarm {
+        let ids: Vec<usize> = (0..particles.len()).collect();
+
+        Swarm {
+            particles,
+            ids,
+            tick: 0,
+        }
+    }
+
+    // Moves all particles, without looking for collisions.
+    fn advance(&mut self) {
+        for particle in self.particles.iter_mut() {
+            particle.update();
+        }
+
+        self.tick += 1;
+    }
+
+    // Moves all particles, then removes the ones which collided. Returns their indices in the
+    // original swarm.
+    fn update(&mut self) -> Vec<usize> {
+        self.advance();
+        self.remove_collisions()
+    }
+
+    // Finds the particles sharing a position by hashing their positions, and removes them.
+    // Returns their indices in the original swarm.
+    fn remove_collisions(&mut self) -> Vec<usize> {
+        let mut positions: HashMap<&Vector, usize> = HashMap::new();
+
+        for particle in self.particles.iter() {
+            *positions.entry(&particle.pos).or_insert(0) += 1;
+        }
+
+        let keep: Vec<bool> = self.particles.iter().map(|p| positions[&p.pos] == 1).collect();
+        let removed: Vec<usize> = self.ids
+            .iter()
+            .zip(keep.iter())
+            .filter(|&(_, &k)| !k)
+            .map(|(&id, _)| id)
+            .collect();
+
+        if !removed.is_empty() {
+            let mut keep_iter = keep.iter();
+            self.particles.retain(|_| *keep_iter.next().unwrap());
+            let mut keep_iter = keep.iter();
+            self.ids.retain(|_| *keep_iter.next().unwrap());
+        }
+
+        removed
+    }
+
+    // Returns true once no two particles can ever collide again.
+    fn is_settled(&self) -> bool {
+        (0..self.particles.len()).all(|i| {
+            (i + 1..self.particles.len()).all(|j| {
+                can_never_meet(&self.particles[i], &self.particles[j])
+            })
+        })
+    }
+
+    // Runs the simulation until the swarm is settled, which always happens eventually.
+    fn run(mut self) -> SimulationResult {
+        let mut last_collision: Option<u64> = None;
+
+        if !self.remove_collisions().is_empty() {
+            last_collision = Some(self.tick);
+        }
+
+        while !self.is_settled() {
+            if !self.update().is_empty() {
+                last_collision = Some(self.tick);
             }
         }
+
+        SimulationResult {
+            last_collision,
+            survivors: self.ids,
+        }
+    }
+}
+
+// The position of a particle at some tick, and whether it gets destroyed by a collision there.
+struct TrajectoryPoint {
+    tick: u64,
+    particle: usize,
+    pos: Vector,
+    collided: bool,
+}
+
+// Simulates the swarm for |ticks| ticks and records the position of every particle at each
+// tick, from 0 to |ticks| included. A particle is recorded up to the tick it collides at.
+fn record_trajectories(swarm: Vec<Particle>, ticks: u64) -> Vec<TrajectoryPoint> {
+    let mut swarm = Swarm::new(swarm);
+    let mut points: Vec<TrajectoryPoint> = Vec::new();
+
+    for tick in 0..ticks + 1 {
+        if tick > 0 {
+            swarm.advance();
+        }
+
+        let first_point = points.len();
+        for (&particle, p) in swarm.ids.iter().zip(swarm.particles.iter()) {
+            points.push(TrajectoryPoint {
+                tick,
+                particle,
+                pos: p.pos.clone(),
+                collided: false,
+            });
+        }
+
+        let destroyed: HashSet<usize> = swarm.remove_collisions().into_iter().collect();
+        for point in points[first_point..].iter_mut() {
+            point.collided = destroyed.contains(&point.particle);
+        }
+    }
+
+    points
+}
+
+// Colour of a point in the 3D exports: collisions are red, other points take the colour of
+// their particle.
+fn point_colour(point: &TrajectoryPoint) -> (u8, u8, u8) {
+    if point.collided {
+        (255, 0, 0)
     } else {
-        for z in sol_z {
-            all_solutions.push(z);
+        distinct_colour(point.particle as u32)
+    }
+}
+
+fn trajectories_to_csv(points: &[TrajectoryPoint]) -> String {
+    let mut csv = String::from("tick,particle,x,y,z,collided\n");
+
+    for point in points {
+        csv.push_str(&format!(
+            "{},{},{},{},{},{}\n",
+            point.tick,
+            point.particle,
+            point.pos.x,
+            point.pos.y,
+            point.pos.z,
+            point.collided as u8
+        ));
+    }
+
+    csv
+}
+
+// A point cloud in the ASCII PLY format, with the particle, tick and collision of each point
+// as extra vertex properties.
+fn trajectories_to_ply(points: &[TrajectoryPoint]) -> String {
+    let mut ply = format!(
+        "ply\nformat ascii 1.0\nelement vertex {}\n\
+         property float x\nproperty float y\nproperty float z\n\
+         property uchar red\nproperty uchar green\nproperty uchar blue\n\
+         property int particle\nproperty int tick\nproperty uchar collided\nend_header\n",
+        points.len()
+    );
+
+    for point in points {
+        let (r, g, b) = point_colour(point);
+        ply.push_str(&format!(
+            "{} {} {} {} {} {} {} {} {}\n",
+            point.pos.x,
+            point.pos.y,
+            point.pos.z,
+            r,
+            g,
+            b,
+            point.particle,
+            point.tick,
+            point.collided as u8
+        ));
+    }
+
+    ply
+}
+
+// A Wavefront OBJ file with one object per particle, made of the points of its trajectory.
+// Vertices carry their colour after the coordinates, which most viewers understand.
+fn trajectories_to_obj(points: &[TrajectoryPoint]) -> String {
+    let mut sorted: Vec<&TrajectoryPoint> = points.iter().collect();
+    sorted.sort_by_key(|p| (p.particle, p.tick));
+
+    let mut obj = String::new();
+    let mut vertex_count = 0;
+
+    for trajectory in sorted.chunk_by(|p1, p2| p1.particle == p2.particle) {
+        let last = trajectory[trajectory.len() - 1];
+        obj.push_str(&format!("o particle_{}\n", last.particle));
+
+        if last.collided {
+            obj.push_str(&format!("# collided at tick {}\n", last.tick));
+        }
+
+        for point in trajectory {
+            let (r, g, b) = point_colour(point);
+            obj.push_str(&format!(
+                "v {} {} {} {:.3} {:.3} {:.3}\n",
+                point.pos.x,
+                point.pos.y,
+                point.pos.z,
+                f64::from(r) / 255.0,
+                f64::from(g) / 255.0,
+                f64::from(b) / 255.0
+            ));
         }
+
+        let indices: Vec<String> = (vertex_count + 1..vertex_count + trajectory.len() + 1)
+            .map(|i| i.to_string())
+            .collect();
+        obj.push_str(&format!("p {}\n", indices.join(" ")));
+        vertex_count += trajectory.len();
     }
 
-    if all_solutions.is_empty() {
-        return None;
+    obj
+}
+
+// Exports the trajectories of the particles for 3D viewers. Usage:
+//   cargo run 20 export <csv|ply|obj> <number of ticks> <output file>
+pub fn day_twenty_export(args: &[String]) {
+    if args.len() != 3 {
+        panic!("Usage: \"cargo run 20 export <csv|ply|obj> <number of ticks> <output file>\"");
     }
 
-    Some(all_solutions)
+    let ticks: u64 = args[1].parse().expect("Expected a number of ticks.");
+    let swarm = parse_swarm(&read_input("data/day_twenty.txt"));
+    let points = record_trajectories(swarm, ticks);
+
+    let contents = match args[0].as_str() {
+        "csv" => trajectories_to_csv(&points),
+        "ply" => trajectories_to_ply(&points),
+        "obj" => trajectories_to_obj(&points),
+        format => panic!("Unknown format {}.", format),
+    };
+
+    write_output(&args[2], &contents);
+
+    println!(
+        "Day 20. {} positions over {} ticks written to {}.",
+        points.len(),
+        ticks,
+        args[2]
+    );
+}
+
+// Runs the swarm tick by tick until no collision can happen, and checks that the result is
+// the same as when solving for the collisions. Usage: "cargo run 20 verify [input file]".
+pub fn day_twenty_verify(args: &[String]) {
+    let path = args.first().map_or("data/day_twenty.txt", |p| p.as_str());
+    let swarm = parse_swarm(&read_input(path));
+    let expected = resolve_collisions(&swarm);
+    let simulated = Swarm::new(swarm).run();
+
+    assert_eq!(expected, simulated, "The simulation disagrees with the solver.");
+    println!(
+        "Day 20. The simulation agrees: {} particles remain, the last collision is at tick {}.",
+        simulated.survivors.len(),
+        simulated.last_collision.map_or(String::from("none"), |t| t.to_string())
+    );
 }
 
 pub fn day_twenty() {
     let contents = read_input("data/day_twenty.txt");
     let swarm: Vec<Particle> = parse_swarm(&contents);
-    use std::u64::MAX;
-    let mut min_distance = MAX;
-    let mut min_particle = 0;
-    let mut index = 0;
+    let ranking = rank_by_long_run_distance(&swarm);
+    let closest: Vec<String> = ranking[0].iter().map(|i| i.to_string()).collect();
 
-    for mut particle in swarm {
-        for _ in 0..10000 {
-            particle.update();
+    println!(
+        "Day 20 part 1. Closest particle has index {}.",
+        closest.join(" or ")
+    );
+    println!(
+        "Day 20. Then come particles {}.",
+        ranking
+            .iter()
+            .skip(1)
+            .take(3)
+            .map(|group| {
+                group.iter().map(|i| i.to_string()).collect::<Vec<String>>().join(" and ")
+            })
+            .collect::<Vec<String>>()
+            .join(", ")
+    );
+
+    let collisions = resolve_collisions(&swarm);
+
+    println!(
+        "Day 20 part 2. {} particles remain.",
+        collisions.survivors.len()
+    );
+    match collisions.last_collision {
+        Some(tick) => println!("Day 20. The last collision happens at tick {}.", tick),
+        None => println!("Day 20. No particles collide."),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A linear congruential generator, so that the checks below are random but reproducible.
+    struct Lcg {
+        state: u64,
+    }
+
+    impl Lcg {
+        fn next(&mut self) -> u64 {
+            self.state = self.state.wrapping_mul(6_364_136_223_846_793_005).wrapping_add(
+                1_442_695_040_888_963_407,
+            );
+            self.state >> 33
         }
 
-        if particle.distance() < min_distance {
-            min_distance = particle.distance();
-            min_particle = index;
+        // Returns a number between -limit and limit, both included.
+        fn next_in(&mut self, limit: i64) -> i64 {
+            (self.next() % (2 * limit as u64 + 1)) as i64 - limit
         }
 
-        index += 1;
+        fn next_vector(&mut self, limit: i64) -> Vector {
+            Vector {
+                x: self.next_in(limit),
+                y: self.next_in(limit),
+                z: self.next_in(limit),
+            }
+        }
+
+        // The limits are small so that many particles do collide. The ticks found by
+        // find_collision are then at most 1 + 4 * POSITION_LIMIT, so simulating TICKS ticks is
+        // enough to see every collision.
+        fn next_particle(&mut self) -> Particle {
+            Particle {
+                pos: self.next_vector(POSITION_LIMIT),
+                vel: self.next_vector(VELOCITY_LIMIT),
+                acc: self.next_vector(ACCELERATION_LIMIT),
+            }
+        }
     }
 
-    println!(
-        "Day 20 part 1. Closest particle has index {}.",
-        min_particle
-    );
+    const POSITION_LIMIT: i64 = 4;
+    const VELOCITY_LIMIT: i64 = 2;
+    const ACCELERATION_LIMIT: i64 = 1;
+    const TICKS: u64 = 100;
+
+    #[test]
+    fn collisions_match_simulation() {
+        let mut rng = Lcg { state: 2017 };
+        let mut collision_count = 0;
+
+        for _ in 0..100_000 {
+            let mut particles: Vec<Particle> = (0..2).map(|_| rng.next_particle()).collect();
+            let expected = find_collision(&particles[0], &particles[1]);
+            let mut simulated: Option<u64> = None;
+
+            for tick in 0..TICKS + 1 {
+                if particles[0].pos == particles[1].pos {
+                    simulated = Some(tick);
+                    break;
+                }
 
-    let swarm2: Vec<Particle> = parse_swarm(&contents);
-    let mut to_remove: HashSet<usize> = HashSet::new();
-
-    for i in 0..swarm2.len() {
-        for j in i + 1..swarm2.len() {
-            let solution = find_collision(&swarm2[i], &swarm2[j]);
-            if solution.is_some() {
-                let mut collisions = solution.unwrap();
-                // Only consider collisions that are both positive, and integer numbers. Such
-                // collision, if it exists, is the time at which the two particles meet.
-                collisions.retain(|&x| x > 0.0 && x.floor() == x);
-
-                if !collisions.is_empty() {
-                    to_remove.insert(i);
-                    to_remove.insert(j);
+                for particle in particles.iter_mut() {
+                    particle.update();
                 }
             }
+
+            assert_eq!(simulated, expected);
+
+            if expected.is_some() {
+                collision_count += 1;
+            }
         }
+
+        // Make sure the particles are not so far apart that the test is pointless.
+        assert!(collision_count > 100);
     }
 
-    println!(
-        "Day 20 part 2. {} particles remain.",
-        swarm2.len() - to_remove.len()
-    );
+    #[test]
+    fn ranking_matches_simulation() {
+        let mut rng = Lcg { state: 2017 };
+
+        // After TICKS ticks, the distances of the particles must already be in the order of
+        // their long run ranking.
+        for _ in 0..2000 {
+            let mut swarm: Vec<Particle> = (0..50).map(|_| rng.next_particle()).collect();
+            let ranking = rank_by_long_run_distance(&swarm);
+
+            for particle in swarm.iter_mut() {
+                for _ in 0..TICKS {
+                    particle.update();
+                }
+            }
+
+            let distances: Vec<Vec<u64>> = ranking
+                .iter()
+                .map(|group| group.iter().map(|&i| swarm[i].distance()).collect())
+                .collect();
+
+            for group in distances.iter() {
+                assert!(group.iter().all(|&d| d == group[0]), "Tied particles drifted apart.");
+            }
+
+            for pair in distances.windows(2) {
+                assert!(pair[0][0] < pair[1][0], "Particles out of order.");
+            }
+        }
+    }
+
+    #[test]
+    fn simulation_matches_collisions() {
+        let mut rng = Lcg { state: 2017 };
+
+        for _ in 0..500 {
+            let swarm: Vec<Particle> = (0..30).map(|_| rng.next_particle()).collect();
+            let expected = resolve_collisions(&swarm);
+
+            assert_eq!(expected, Swarm::new(swarm).run());
+        }
+    }
+
+    #[test]
+    fn puzzle_example() {
+        let swarm = parse_swarm(
+            "p=<-6,0,0>, v=<3,0,0>, a=<0,0,0>\n\
+             p=<-4,0,0>, v=<2,0,0>, a=<0,0,0>\n\
+             p=<-2,0,0>, v=<1,0,0>, a=<0,0,0>\n\
+             p=<3,0,0>, v=<-1,0,0>, a=<0,0,0>\n",
+        );
+        let result = resolve_collisions(&swarm);
+
+        assert_eq!(vec![3], result.survivors);
+        assert_eq!(Some(2), result.last_collision);
+    }
 }
@@ -1,4 +1,5 @@
 use utils::read_input;
+use std::collections::HashMap;
 
 #[derive(Default, PartialEq, Clone)]
 struct Grid {
@@ -76,6 +77,31 @@ impl Grid {
         }
     }
 
+    // Packs a square of at most 4x4 pixels in an integer, one bit per pixel, row by row.
+    fn to_mask(&self) -> u16 {
+        let mut mask: u16 = 0;
+
+        for (i, ch) in self.grid.iter().flat_map(|row| row.iter()).enumerate() {
+            if *ch == '#' {
+                mask |= 1 << i;
+            }
+        }
+
+        mask
+    }
+
+    fn from_mask(mask: u16, size: usize) -> Grid {
+        let grid: Vec<Vec<char>> = (0..size)
+            .map(|row| {
+                (0..size)
+                    .map(|col| if mask & (1 << (row * size + col)) != 0 { '#' } else { '.' })
+                    .collect()
+            })
+            .collect();
+
+        Grid::new(grid)
+    }
+
     fn count_on_pixels(&self) -> u32 {
         let mut count: u32 = 0;
 
@@ -100,10 +126,6 @@ impl Rule {
     fn new(input: Grid, output: Grid) -> Rule {
         Rule { input, output }
     }
-
-    fn is_match(&self, grid: &Grid) -> bool {
-        return self.input == *grid;
-    }
 }
 
 fn parse_rules() -> Vec<Rule> {
@@ -152,14 +174,15 @@ fn flip_grid_vertical(grid: &Grid) -> Grid {
     Grid::new(new)
 }
 
-fn flip_grid_horizontal(grid: &Grid) -> Grid {
+// Rotates a grid by 90 degrees to the right.
+fn rotate_grid(grid: &Grid) -> Grid {
     let mut new: Vec<Vec<char>> = Vec::with_capacity(grid.grid.len());
 
     for i in 0..grid.grid.len() {
         let mut row: Vec<char> = Vec::with_capacity(grid.grid.len());
 
         for j in 0..grid.grid.len() {
-            row.push(grid.grid[grid.grid.len() - i - 1][j]);
+            row.push(grid.grid[grid.grid.len() - j - 1][i]);
         }
 
         new.push(row);
@@ -168,105 +191,171 @@ fn flip_grid_horizontal(grid: &Grid) -> Grid {
     Grid::new(new)
 }
 
-// Rotates a grid by 90 degrees to the right.
-fn rotate_grid(grid: &Grid) -> Grid {
-    let mut new: Vec<Vec<char>> = Vec::with_capacity(grid.grid.len());
+// Identifies a square up to rotations and flips: its size, and the smallest of the masks of its
+// 8 symmetries, i.e. its 4 rotations with and without flipping it.
+fn canonical_key(square: &Grid) -> (usize, u16) {
+    let mut min_mask = u16::MAX;
+    let mut current = square.clone();
 
-    for i in 0..grid.grid.len() {
-        let mut row: Vec<char> = Vec::with_capacity(grid.grid.len());
-
-        for j in 0..grid.grid.len() {
-            row.push(grid.grid[grid.grid.len() - j - 1][i]);
-        }
-
-        new.push(row);
+    for _ in 0..4 {
+        min_mask = min_mask.min(current.to_mask()).min(flip_grid_vertical(&current).to_mask());
+        current = rotate_grid(&current);
     }
 
-    Grid::new(new)
+    (square.grid.len(), min_mask)
 }
 
-fn find_rule(rules: &Vec<Rule>, square: &Grid) -> Option<Grid> {
-    let flip_v = flip_grid_vertical(square);
-    let flip_h = flip_grid_horizontal(square);
-    let rotate_90 = rotate_grid(square);
-    let rotate_180 = rotate_grid(&rotate_90);
-    let rotate_270 = rotate_grid(&rotate_180);
-
-    let rotate_flip_v90 = rotate_grid(&flip_v);
-    let rotate_flip_v180 = rotate_grid(&rotate_flip_v90);
-    let rotate_flip_v270 = rotate_grid(&rotate_flip_v180);
-
-    // You could also include rotations after a horizontal flip, but it seems to generate
-    // duplicates with regular no-flip rotations, and they are not required on my
-    // input.
-    for rule in rules {
-        if rule.is_match(square) || rule.is_match(&flip_v) || rule.is_match(&flip_h) ||
-            rule.is_match(&rotate_90) || rule.is_match(&rotate_180) ||
-            rule.is_match(&rotate_270) ||
-            rule.is_match(&rotate_flip_v90) || rule.is_match(&rotate_flip_v180) ||
-            rule.is_match(&rotate_flip_v270)
-        {
-            return Some(rule.output.clone());
+// Applies the rules to grids. Rules are looked up by the canonical key of their input, so
+// that rotating and flipping squares to find a match is not needed.
+//
+// A 3x3 square becomes a 4x4 square, then four 2x2 squares becoming 3x3 squares, then a 6x6
+// square which splits into 2x2 squares and becomes 9x9. That is nine 3x3 squares, each of
+// which evolves on its own from then on. So every three iterations we only need to know how
+// many of each 3x3 square there are, and how each of them expands, which is memoized.
+struct Enhancer {
+    rules: HashMap<(usize, u16), Grid>,
+    expansions: HashMap<u16, Vec<u16>>,
+}
+
+impl Enhancer {
+    fn new(rules: &[Rule]) -> Enhancer {
+        let rules: HashMap<(usize, u16), Grid> = rules
+            .iter()
+            .map(|rule| (canonical_key(&rule.input), rule.output.clone()))
+            .collect();
+
+        Enhancer {
+            rules,
+            expansions: HashMap::new(),
         }
     }
 
-    None
-}
+    fn enhance_square(&self, square: &Grid) -> &Grid {
+        self.rules.get(&canonical_key(square)).expect(
+            "Could not find matching rule.",
+        )
+    }
 
-pub fn day_twenty_one() {
-    let rules: Vec<Rule> = parse_rules();
-    let start_pattern = ".#./..#/###";
-    let mut main_grid = Grid::from_string(&start_pattern);
+    // Runs one iteration on the whole grid.
+    fn enhance(&self, grid: &Grid) -> Grid {
+        let size = if grid.grid.len().is_multiple_of(2) {
+            2
+        } else if grid.grid.len().is_multiple_of(3) {
+            3
+        } else {
+            panic!("Grid length has unexpected value of {}.", grid.grid.len());
+        };
 
-    for iteration in 0..18 {
+        let squares_per_row = grid.grid.len() / size;
         let mut new_grid: Grid = Default::default();
 
-        if main_grid.grid.len() % 2 == 0 {
-            let mut square_index = 0;
-            let mut row_number = 0;
+        for (square_index, square) in grid.iter_squares(size).iter().enumerate() {
+            new_grid.add_square(self.enhance_square(square), square_index / squares_per_row);
+        }
 
-            for square in main_grid.iter_squares(2) {
-                // Finds the output square for this one.
-                let new_square = find_rule(&rules, &square).expect("Could not find matching rule.");
-                new_grid.add_square(&new_square, row_number);
-                square_index += 1;
+        new_grid
+    }
 
-                if square_index % (main_grid.grid.len() / square.grid.len()) == 0 {
-                    row_number += 1;
-                }
-            }
-        } else if main_grid.grid.len() % 3 == 0 {
-            let mut square_index = 0;
-            let mut row_number = 0;
+    // Returns the nine 3x3 squares a 3x3 square becomes after three iterations.
+    fn expand(&mut self, square: u16) -> Vec<u16> {
+        if let Some(expansion) = self.expansions.get(&square) {
+            return expansion.clone();
+        }
+
+        let mut grid = Grid::from_mask(square, 3);
+        for _ in 0..3 {
+            grid = self.enhance(&grid);
+        }
+
+        let expansion: Vec<u16> = grid.iter_squares(3).iter().map(|s| s.to_mask()).collect();
+        self.expansions.insert(square, expansion.clone());
+        expansion
+    }
 
-            for square in main_grid.iter_squares(3) {
-                let new_square = find_rule(&rules, &square).expect("Could not find matching rule.");
-                new_grid.add_square(&new_square, row_number);
-                square_index += 1;
+    // Counts the pixels on after some iterations, starting from a 3x3 pattern.
+    fn count_on_pixels(&mut self, start: &Grid, iterations: usize) -> u64 {
+        assert_eq!(3, start.grid.len(), "The starting pattern must be 3x3.");
 
-                if square_index % (main_grid.grid.len() / square.grid.len()) == 0 {
-                    row_number += 1;
+        let mut squares: HashMap<u16, u64> = HashMap::new();
+        squares.insert(start.to_mask(), 1);
+
+        for _ in 0..iterations / 3 {
+            let mut next_squares: HashMap<u16, u64> = HashMap::new();
+
+            for (&square, &count) in squares.iter() {
+                for next_square in self.expand(square) {
+                    *next_squares.entry(next_square).or_insert(0) += count;
                 }
             }
-        } else {
-            panic!(
-                "Grid length has unexpected value of {}.",
-                main_grid.grid.len()
-            );
+
+            squares = next_squares;
         }
 
-        main_grid = new_grid;
+        // Fewer than three iterations are left: run them on each distinct square.
+        let mut total: u64 = 0;
+        for (&square, &count) in squares.iter() {
+            let mut grid = Grid::from_mask(square, 3);
+            for _ in 0..iterations % 3 {
+                grid = self.enhance(&grid);
+            }
 
-        if iteration == 4 {
-            println!(
-                "Day 21 part 1. Number of \"on\" pixels is {}.",
-                main_grid.count_on_pixels()
-            );
+            total += count * u64::from(grid.count_on_pixels());
         }
+
+        total
     }
+}
+
+pub fn day_twenty_one() {
+    let rules: Vec<Rule> = parse_rules();
+    let start_pattern = ".#./..#/###";
+    let start = Grid::from_string(start_pattern);
+    let mut enhancer = Enhancer::new(&rules);
 
+    println!(
+        "Day 21 part 1. Number of \"on\" pixels is {}.",
+        enhancer.count_on_pixels(&start, 5)
+    );
     println!(
         "Day 21 part 2. Number of \"on\" pixels is {}.",
-        main_grid.count_on_pixels()
+        enhancer.count_on_pixels(&start, 18)
+    );
+    println!(
+        "Day 21. After 36 iterations, {} pixels are on.",
+        enhancer.count_on_pixels(&start, 36)
     );
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn puzzle_example() {
+        let rules = vec![
+            Rule::new(Grid::from_string("../.#"), Grid::from_string("##./#../...")),
+            Rule::new(
+                Grid::from_string(".#./..#/###"),
+                Grid::from_string("#..#/..../..../#..#"),
+            ),
+        ];
+        let mut enhancer = Enhancer::new(&rules);
+
+        assert_eq!(enhancer.count_on_pixels(&Grid::from_string(".#./..#/###"), 2), 12);
+    }
+
+    #[test]
+    fn counting_matches_full_grid() {
+        let mut enhancer = Enhancer::new(&parse_rules());
+        let start = Grid::from_string(".#./..#/###");
+        let mut grid = start.clone();
+
+        for iterations in 0..8 {
+            assert_eq!(
+                enhancer.count_on_pixels(&start, iterations),
+                u64::from(grid.count_on_pixels())
+            );
+            grid = enhancer.enhance(&grid);
+        }
+    }
+}
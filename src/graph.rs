@@ -0,0 +1,248 @@
+// Tools for undirected graphs stored as adjacency lists, like the pipes of day 12. Nodes that
+// only appear as neighbours are still part of the graph, with no neighbours of their own.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+
+pub type AdjacencyList = HashMap<i32, Vec<i32>>;
+
+// Returns all the nodes of the graph, sorted.
+pub fn nodes(graph: &AdjacencyList) -> Vec<i32> {
+    let mut all: HashSet<i32> = graph.keys().cloned().collect();
+
+    for neighbours in graph.values() {
+        all.extend(neighbours.iter());
+    }
+
+    let mut result: Vec<i32> = all.into_iter().collect();
+    result.sort();
+    result
+}
+
+fn neighbours(graph: &AdjacencyList, node: i32) -> &[i32] {
+    graph.get(&node).map_or(&[], |n| n.as_slice())
+}
+
+// Returns the edges (a, b) such that b is listed among the neighbours of a but not the other
+// way around. In an undirected graph there should be none.
+pub fn asymmetric_edges(graph: &AdjacencyList) -> Vec<(i32, i32)> {
+    let mut result: Vec<(i32, i32)> = Vec::new();
+
+    for node in nodes(graph) {
+        for &neighbour in neighbours(graph, node) {
+            if !neighbours(graph, neighbour).contains(&node) {
+                result.push((node, neighbour));
+            }
+        }
+    }
+
+    result
+}
+
+// Returns a shortest path from |from| to |to|, both included, found with a breadth-first
+// search. Returns None if |to| cannot be reached.
+pub fn shortest_path(graph: &AdjacencyList, from: i32, to: i32) -> Option<Vec<i32>> {
+    let mut previous: HashMap<i32, i32> = HashMap::new();
+    let mut queue: VecDeque<i32> = VecDeque::new();
+    let mut seen: HashSet<i32> = HashSet::new();
+
+    queue.push_back(from);
+    seen.insert(from);
+
+    while let Some(node) = queue.pop_front() {
+        if node == to {
+            let mut path = vec![to];
+            let mut current = to;
+
+            while let Some(&p) = previous.get(&current) {
+                path.push(p);
+                current = p;
+            }
+
+            path.reverse();
+            return Some(path);
+        }
+
+        for &neighbour in neighbours(graph, node) {
+            if seen.insert(neighbour) {
+                previous.insert(neighbour, node);
+                queue.push_back(neighbour);
+            }
+        }
+    }
+
+    None
+}
+
+// The articulation points and bridges of a graph: removing any of them splits a connected
+// component in two or more.
+pub struct CutStructure {
+    pub articulation_points: Vec<i32>,
+    pub bridges: Vec<(i32, i32)>,
+}
+
+// A node being visited by the depth-first search of find_cuts.
+struct Frame {
+    node: i32,
+    parent: Option<i32>,
+    next_neighbour: usize,
+    skipped_parent: bool,
+    children: usize,
+}
+
+// Finds articulation points and bridges with Tarjan's algorithm. Each node is discovered at
+// some time during a depth-first search, and |low| is the earliest discovery time reachable
+// from its subtree using at most one edge that is not in the search tree. A child whose
+// subtree cannot reach above its parent depends on the parent to reach the rest of the graph.
+//
+// The search keeps its own stack rather than recursing, so that long chains of nodes do not
+// overflow the call stack. The graph is treated as undirected, so it should be symmetric.
+pub fn find_cuts(graph: &AdjacencyList) -> CutStructure {
+    let mut discovery: HashMap<i32, usize> = HashMap::new();
+    let mut low: HashMap<i32, usize> = HashMap::new();
+    let mut articulation_points: HashSet<i32> = HashSet::new();
+    let mut bridges: Vec<(i32, i32)> = Vec::new();
+    let mut time = 0;
+
+    for root in nodes(graph) {
+        if discovery.contains_key(&root) {
+            continue;
+        }
+
+        let mut stack: Vec<Frame> = vec![
+            Frame {
+                node: root,
+                parent: None,
+                next_neighbour: 0,
+                skipped_parent: false,
+                children: 0,
+            },
+        ];
+        discovery.insert(root, time);
+        low.insert(root, time);
+        time += 1;
+
+        while !stack.is_empty() {
+            let top = stack.len() - 1;
+            let node = stack[top].node;
+            let node_neighbours = neighbours(graph, node);
+
+            if stack[top].next_neighbour < node_neighbours.len() {
+                let neighbour = node_neighbours[stack[top].next_neighbour];
+                stack[top].next_neighbour += 1;
+
+                // Going back along the edge we came from does not count, but a second edge
+                // towards the parent does.
+                if Some(neighbour) == stack[top].parent && !stack[top].skipped_parent {
+                    stack[top].skipped_parent = true;
+                    continue;
+                }
+
+                if neighbour == node {
+                    continue;
+                }
+
+                match discovery.get(&neighbour) {
+                    Some(&neighbour_discovery) => {
+                        let node_low = low[&node].min(neighbour_discovery);
+                        low.insert(node, node_low);
+                    }
+                    None => {
+                        discovery.insert(neighbour, time);
+                        low.insert(neighbour, time);
+                        time += 1;
+                        stack[top].children += 1;
+                        stack.push(Frame {
+                            node: neighbour,
+                            parent: Some(node),
+                            next_neighbour: 0,
+                            skipped_parent: false,
+                            children: 0,
+                        });
+                    }
+                }
+
+                continue;
+            }
+
+            let frame = stack.pop().unwrap();
+
+            match frame.parent {
+                Some(parent) => {
+                    let parent_low = low[&parent].min(low[&node]);
+                    low.insert(parent, parent_low);
+
+                    if low[&node] > discovery[&parent] {
+                        bridges.push((parent.min(node), parent.max(node)));
+                    }
+
+                    // The root is handled separately, since nothing comes before it.
+                    if stack.len() > 1 && low[&node] >= discovery[&parent] {
+                        articulation_points.insert(parent);
+                    }
+                }
+                None => {
+                    if frame.children > 1 {
+                        articulation_points.insert(node);
+                    }
+                }
+            }
+        }
+    }
+
+    let mut articulation_points: Vec<i32> = articulation_points.into_iter().collect();
+    articulation_points.sort();
+    bridges.sort();
+
+    CutStructure {
+        articulation_points,
+        bridges,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Builds a symmetric graph. An edge listed twice becomes two parallel edges.
+    fn graph(edges: &[(i32, i32)]) -> AdjacencyList {
+        let mut result = AdjacencyList::new();
+        for &(a, b) in edges {
+            result.entry(a).or_default().push(b);
+            result.entry(b).or_default().push(a);
+        }
+        result
+    }
+
+    #[test]
+    fn cuts_on_a_path() {
+        let cuts = find_cuts(&graph(&[(1, 2), (2, 3), (3, 4)]));
+
+        assert_eq!(cuts.articulation_points, vec![2, 3]);
+        assert_eq!(cuts.bridges, vec![(1, 2), (2, 3), (3, 4)]);
+    }
+
+    #[test]
+    fn cuts_on_a_cycle() {
+        let cuts = find_cuts(&graph(&[(1, 2), (2, 3), (3, 1), (3, 4)]));
+
+        assert_eq!(cuts.articulation_points, vec![3]);
+        assert_eq!(cuts.bridges, vec![(3, 4)]);
+    }
+
+    #[test]
+    fn parallel_edges_are_not_bridges() {
+        let cuts = find_cuts(&graph(&[(1, 2), (1, 2), (2, 3)]));
+
+        assert_eq!(cuts.articulation_points, vec![2]);
+        assert_eq!(cuts.bridges, vec![(2, 3)]);
+    }
+
+    #[test]
+    fn shortest_paths() {
+        let pipes = graph(&[(1, 2), (2, 3), (3, 4), (1, 5), (5, 4), (6, 7)]);
+
+        assert_eq!(shortest_path(&pipes, 1, 4), Some(vec![1, 5, 4]));
+        assert_eq!(shortest_path(&pipes, 2, 2), Some(vec![2]));
+        assert_eq!(shortest_path(&pipes, 1, 6), None);
+    }
+}
@@ -0,0 +1,207 @@
+// Connected-component labeling over 2D grids, using the classic two-pass algorithm: the first
+// pass assigns provisional labels scanning the grid in raster order and records which labels
+// touch each other in a union-find structure; the second pass replaces each provisional label
+// with the final one. Only the previous row is ever inspected, so there is no recursion or
+// explicit stack to grow on large grids.
+
+use union_find::UnionFind;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Connectivity {
+    // Cells are connected only horizontally and vertically.
+    Four,
+    // Cells are also connected diagonally.
+    Eight,
+}
+
+// A connected component, with its size and bounding box (inclusive on both ends).
+pub struct Component {
+    pub label: u32,
+    pub size: usize,
+    pub min_row: usize,
+    pub min_col: usize,
+    pub max_row: usize,
+    pub max_col: usize,
+}
+
+// Result of the labeling. Unoccupied cells have label 0, and components are numbered from 1
+// in the order in which they are first met scanning the grid row by row. The component with
+// label l is at index l - 1 in |components|.
+pub struct Labeling {
+    pub labels: Vec<Vec<u32>>,
+    pub components: Vec<Component>,
+}
+
+// Returns the provisional labels of the neighbours already visited by the first pass, i.e.
+// those on the left and on the previous row. Rows may have different lengths.
+fn visited_neighbours(
+    provisional: &[Vec<Option<usize>>],
+    row: usize,
+    col: usize,
+    connectivity: Connectivity,
+) -> Vec<usize> {
+    let mut result: Vec<usize> = Vec::new();
+
+    if col > 0 {
+        if let Some(label) = provisional[row][col - 1] {
+            result.push(label);
+        }
+    }
+
+    if row > 0 {
+        let previous = &provisional[row - 1];
+        let first_col = if connectivity == Connectivity::Eight && col > 0 {
+            col - 1
+        } else {
+            col
+        };
+        let last_col = if connectivity == Connectivity::Eight {
+            col + 1
+        } else {
+            col
+        };
+
+        for c in first_col..last_col + 1 {
+            if let Some(&Some(label)) = previous.get(c) {
+                result.push(label);
+            }
+        }
+    }
+
+    result
+}
+
+// Labels the connected components of the cells of |grid| for which |is_occupied| returns true.
+pub fn label_components<T, F>(
+    grid: &[Vec<T>],
+    connectivity: Connectivity,
+    is_occupied: F,
+) -> Labeling
+where
+    F: Fn(&T) -> bool,
+{
+    let mut sets = UnionFind::new();
+    let mut provisional: Vec<Vec<Option<usize>>> = Vec::with_capacity(grid.len());
+
+    // First pass: each occupied cell takes the smallest label among its visited neighbours,
+    // or a new one if there are none, and all the neighbouring labels are merged.
+    for (row_index, row) in grid.iter().enumerate() {
+        provisional.push(Vec::with_capacity(row.len()));
+
+        for (col_index, cell) in row.iter().enumerate() {
+            if !is_occupied(cell) {
+                provisional[row_index].push(None);
+                continue;
+            }
+
+            let neighbours = visited_neighbours(&provisional, row_index, col_index, connectivity);
+            let label = match neighbours.iter().min() {
+                Some(&min) => min,
+                None => sets.make_set(),
+            };
+
+            for n in neighbours {
+                sets.union(label, n);
+            }
+
+            provisional[row_index].push(Some(label));
+        }
+    }
+
+    // Second pass: replace provisional labels with the final ones, and collect the
+    // statistics of each component.
+    let mut final_labels: Vec<u32> = vec![0; sets.len()];
+    let mut components: Vec<Component> = Vec::new();
+    let mut labels: Vec<Vec<u32>> = Vec::with_capacity(grid.len());
+
+    for (row_index, row) in provisional.iter().enumerate() {
+        let mut new_row: Vec<u32> = Vec::with_capacity(row.len());
+
+        for (col_index, cell) in row.iter().enumerate() {
+            let root = match *cell {
+                Some(label) => sets.find(label),
+                None => {
+                    new_row.push(0);
+                    continue;
+                }
+            };
+
+            if final_labels[root] == 0 {
+                components.push(Component {
+                    label: components.len() as u32 + 1,
+                    size: 0,
+                    min_row: row_index,
+                    min_col: col_index,
+                    max_row: row_index,
+                    max_col: col_index,
+                });
+                final_labels[root] = components.len() as u32;
+            }
+
+            let label = final_labels[root];
+            let component = &mut components[label as usize - 1];
+            component.size += 1;
+            component.min_col = component.min_col.min(col_index);
+            component.max_col = component.max_col.max(col_index);
+            component.max_row = row_index;
+
+            new_row.push(label);
+        }
+
+        labels.push(new_row);
+    }
+
+    Labeling { labels, components }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grid(rows: &[&str]) -> Vec<Vec<char>> {
+        rows.iter().map(|row| row.chars().collect()).collect()
+    }
+
+    #[test]
+    fn diagonal_neighbours() {
+        let cells = grid(&["#.", ".#"]);
+
+        let four = label_components(&cells, Connectivity::Four, |&c| c == '#');
+        assert_eq!(four.components.len(), 2);
+        assert_eq!(four.labels, vec![vec![1, 0], vec![0, 2]]);
+
+        let eight = label_components(&cells, Connectivity::Eight, |&c| c == '#');
+        assert_eq!(eight.components.len(), 1);
+        assert_eq!(eight.labels, vec![vec![1, 0], vec![0, 1]]);
+    }
+
+    #[test]
+    fn ragged_rows() {
+        let cells = grid(&["##", "#", "#..#", "...#"]);
+        let labeling = label_components(&cells, Connectivity::Eight, |&c| c == '#');
+
+        assert_eq!(
+            labeling.labels,
+            vec![vec![1, 1], vec![1], vec![1, 0, 0, 2], vec![0, 0, 0, 2]]
+        );
+        assert_eq!(labeling.components[0].size, 4);
+        assert_eq!(labeling.components[1].size, 2);
+    }
+
+    #[test]
+    fn bounding_boxes() {
+        // The U shape gets two provisional labels that are only merged on the last row.
+        let cells = grid(&["#..#.", "#..#.", "####.", "....#"]);
+        let labeling = label_components(&cells, Connectivity::Four, |&c| c == '#');
+
+        assert_eq!(labeling.components.len(), 2);
+
+        let u = &labeling.components[0];
+        assert_eq!((u.label, u.size), (1, 8));
+        assert_eq!((u.min_row, u.min_col, u.max_row, u.max_col), (0, 0, 2, 3));
+
+        let dot = &labeling.components[1];
+        assert_eq!((dot.label, dot.size), (2, 1));
+        assert_eq!((dot.min_row, dot.min_col, dot.max_row, dot.max_col), (3, 4, 3, 4));
+    }
+}
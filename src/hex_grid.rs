@@ -0,0 +1,300 @@
+// Hexagonal grids with flat-topped hexagons, as in day 11: each hex has neighbours to the
+// north, north-east, south-east, south, south-west and north-west.
+//
+// Hexes are stored in cube coordinates: three axes at 120 degrees from each other, with the
+// constraint q + r + s = 0. Moving to a neighbour changes two of the coordinates by one in
+// opposite directions, which makes distances and lines easy to compute.
+
+use std::ops::{Add, Sub};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HexDirection {
+    North,
+    NorthEast,
+    SouthEast,
+    South,
+    SouthWest,
+    NorthWest,
+}
+
+impl HexDirection {
+    // All directions, clockwise starting from north.
+    pub fn all() -> [HexDirection; 6] {
+        [
+            HexDirection::North,
+            HexDirection::NorthEast,
+            HexDirection::SouthEast,
+            HexDirection::South,
+            HexDirection::SouthWest,
+            HexDirection::NorthWest,
+        ]
+    }
+
+    // Parses a direction in the format of the puzzle, e.g. "ne". Surrounding whitespace is
+    // ignored.
+    pub fn parse(s: &str) -> Result<HexDirection, String> {
+        match s.trim() {
+            "n" => Ok(HexDirection::North),
+            "ne" => Ok(HexDirection::NorthEast),
+            "se" => Ok(HexDirection::SouthEast),
+            "s" => Ok(HexDirection::South),
+            "sw" => Ok(HexDirection::SouthWest),
+            "nw" => Ok(HexDirection::NorthWest),
+            _ => Err(format!("Unrecognized direction \"{}\".", s.trim())),
+        }
+    }
+
+    // The name of the direction in the format of the puzzle.
+    pub fn name(&self) -> &'static str {
+        match *self {
+            HexDirection::North => "n",
+            HexDirection::NorthEast => "ne",
+            HexDirection::SouthEast => "se",
+            HexDirection::South => "s",
+            HexDirection::SouthWest => "sw",
+            HexDirection::NorthWest => "nw",
+        }
+    }
+
+    // The change in coordinates when moving one step in this direction.
+    pub fn offset(&self) -> Hex {
+        match *self {
+            HexDirection::North => Hex::new(0, -1, 1),
+            HexDirection::NorthEast => Hex::new(1, -1, 0),
+            HexDirection::SouthEast => Hex::new(1, 0, -1),
+            HexDirection::South => Hex::new(0, 1, -1),
+            HexDirection::SouthWest => Hex::new(-1, 1, 0),
+            HexDirection::NorthWest => Hex::new(-1, 0, 1),
+        }
+    }
+}
+
+// Parses a comma-separated list of directions.
+pub fn parse_directions(input: &str) -> Result<Vec<HexDirection>, String> {
+    input
+        .split(',')
+        .filter(|d| !d.trim().is_empty())
+        .map(HexDirection::parse)
+        .collect()
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Hex {
+    pub q: i64,
+    pub r: i64,
+    pub s: i64,
+}
+
+impl Hex {
+    pub fn new(q: i64, r: i64, s: i64) -> Hex {
+        assert_eq!(0, q + r + s, "Cube coordinates must sum to 0.");
+        Hex { q, r, s }
+    }
+
+    pub fn origin() -> Hex {
+        Hex::new(0, 0, 0)
+    }
+
+    pub fn neighbour(&self, direction: HexDirection) -> Hex {
+        *self + direction.offset()
+    }
+
+    // The six neighbours of this hex, in the order of HexDirection::all().
+    pub fn neighbours(&self) -> Vec<Hex> {
+        HexDirection::all()
+            .iter()
+            .map(|&d| self.neighbour(d))
+            .collect()
+    }
+
+    // Number of steps needed to reach the origin.
+    pub fn length(&self) -> i64 {
+        self.q.abs().max(self.r.abs()).max(self.s.abs())
+    }
+
+    pub fn distance(&self, other: &Hex) -> i64 {
+        (*self - *other).length()
+    }
+
+    // Returns all the hexes at distance |radius| from this one, going clockwise.
+    pub fn ring(&self, radius: i64) -> Vec<Hex> {
+        if radius == 0 {
+            return vec![*self];
+        }
+
+        let mut result: Vec<Hex> = Vec::with_capacity(6 * radius as usize);
+        let mut current = *self;
+
+        for _ in 0..radius {
+            current = current.neighbour(HexDirection::NorthWest);
+        }
+
+        for &direction in HexDirection::all().iter().skip(1) {
+            for _ in 0..radius {
+                result.push(current);
+                current = current.neighbour(direction);
+            }
+        }
+
+        for _ in 0..radius {
+            result.push(current);
+            current = current.neighbour(HexDirection::North);
+        }
+
+        result
+    }
+
+    // Returns a shortest sequence of moves leading from this hex to |other|. There are usually
+    // many, so we always pick the same one: at each step we move in the first direction,
+    // clockwise from north, that gets us closer.
+    pub fn path_to(&self, other: &Hex) -> Vec<HexDirection> {
+        let mut result: Vec<HexDirection> = Vec::with_capacity(self.distance(other) as usize);
+        let mut current = *self;
+
+        while current != *other {
+            let distance = current.distance(other);
+            let (direction, next) = HexDirection::all()
+                .iter()
+                .cloned()
+                .zip(current.neighbours())
+                .find(|(_, n)| n.distance(other) < distance)
+                .expect("There is always a direction getting closer.");
+
+            result.push(direction);
+            current = next;
+        }
+
+        result
+    }
+
+    // Returns the hexes crossed by the straight line from this hex to |other|, both included.
+    // Consecutive hexes in the result are always neighbours.
+    pub fn line_to(&self, other: &Hex) -> Vec<Hex> {
+        let steps = self.distance(other);
+        let mut result: Vec<Hex> = Vec::with_capacity(steps as usize + 1);
+
+        for i in 0..steps + 1 {
+            let t = if steps == 0 {
+                0.0
+            } else {
+                i as f64 / steps as f64
+            };
+
+            // The tiny offsets keep points from falling exactly on the edge between hexes,
+            // so that ties are always broken in the same direction.
+            result.push(Hex::round(
+                lerp(self.q as f64 + 1e-6, other.q as f64 + 1e-6, t),
+                lerp(self.r as f64 + 2e-6, other.r as f64 + 2e-6, t),
+                lerp(self.s as f64 - 3e-6, other.s as f64 - 3e-6, t),
+            ));
+        }
+
+        result
+    }
+
+    // Returns the hex containing the point with the given fractional cube coordinates.
+    fn round(q: f64, r: f64, s: f64) -> Hex {
+        let mut rounded_q = q.round();
+        let mut rounded_r = r.round();
+        let mut rounded_s = s.round();
+
+        // Rounding may break the constraint on the sum: fix the coordinate that changed the
+        // most from its fractional value.
+        let q_diff = (rounded_q - q).abs();
+        let r_diff = (rounded_r - r).abs();
+        let s_diff = (rounded_s - s).abs();
+
+        if q_diff > r_diff && q_diff > s_diff {
+            rounded_q = -rounded_r - rounded_s;
+        } else if r_diff > s_diff {
+            rounded_r = -rounded_q - rounded_s;
+        } else {
+            rounded_s = -rounded_q - rounded_r;
+        }
+
+        Hex::new(rounded_q as i64, rounded_r as i64, rounded_s as i64)
+    }
+}
+
+fn lerp(a: f64, b: f64, t: f64) -> f64 {
+    a + (b - a) * t
+}
+
+impl Add for Hex {
+    type Output = Hex;
+
+    fn add(self, other: Hex) -> Hex {
+        Hex::new(self.q + other.q, self.r + other.r, self.s + other.s)
+    }
+}
+
+impl Sub for Hex {
+    type Output = Hex;
+
+    fn sub(self, other: Hex) -> Hex {
+        Hex::new(self.q - other.q, self.r - other.r, self.s - other.s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn neighbours_are_at_distance_one() {
+        let hex = Hex::new(2, -5, 3);
+        let neighbours = hex.neighbours();
+
+        assert_eq!(neighbours.len(), 6);
+        for (direction, neighbour) in HexDirection::all().iter().zip(neighbours.iter()) {
+            assert_eq!(hex.distance(neighbour), 1);
+            assert_eq!(*neighbour, hex.neighbour(*direction));
+        }
+    }
+
+    #[test]
+    fn ring_contains_all_hexes_at_radius() {
+        let center = Hex::new(-1, 4, -3);
+        assert_eq!(center.ring(0), vec![center]);
+
+        for radius in 1..6 {
+            let ring = center.ring(radius);
+            assert_eq!(ring.len(), 6 * radius as usize);
+
+            for (i, hex) in ring.iter().enumerate() {
+                assert_eq!(center.distance(hex), radius);
+                assert_eq!(hex.distance(&ring[(i + 1) % ring.len()]), 1);
+            }
+
+            let mut distinct = ring.clone();
+            distinct.sort_by_key(|h| (h.q, h.r));
+            distinct.dedup();
+            assert_eq!(distinct.len(), ring.len());
+        }
+    }
+
+    #[test]
+    fn line_goes_through_neighbours() {
+        let start = Hex::new(1, -3, 2);
+        for end in (0..8).flat_map(|radius| start.ring(radius)) {
+            let line = start.line_to(&end);
+
+            assert_eq!(line.len() as i64, start.distance(&end) + 1);
+            assert_eq!(line[0], start);
+            assert_eq!(line[line.len() - 1], end);
+            for pair in line.windows(2) {
+                assert_eq!(pair[0].distance(&pair[1]), 1);
+            }
+        }
+    }
+
+    #[test]
+    fn path_leads_to_target() {
+        let start = Hex::new(3, 0, -3);
+        let end = Hex::new(-4, 6, -2);
+        let path = start.path_to(&end);
+
+        assert_eq!(path.len() as i64, start.distance(&end));
+        assert_eq!(path.iter().fold(start, |h, &d| h.neighbour(d)), end);
+    }
+}
@@ -74,6 +74,12 @@ mod day_twenty_five;
 use day_twenty_five::*;
 
 mod utils;
+mod grid_labeling;
+mod spiral;
+mod cycle_detection;
+mod hex_grid;
+mod union_find;
+mod graph;
 
 use std::env;
 
@@ -113,16 +119,37 @@ fn day_twenty_two_wrapper() {
     day_twenty_two_part_two();
 }
 
+// Some days also offer extra tools, such as visualizations or exports. Each tool receives
+// the command-line arguments that follow its name.
+fn run_tool(day_num: usize, tool: &str, tool_args: &[String]) {
+    match (day_num, tool) {
+        (6, "bench") => day_six_bench(tool_args),
+        (7, "dot") => day_seven_dot(tool_args),
+        (11, "svg") => day_eleven_svg(tool_args),
+        (12, "query") => day_twelve_query(tool_args),
+        (12, "export") => day_twelve_export(tool_args),
+        (12, "path") => day_twelve_path(tool_args),
+        (12, "cuts") => day_twelve_cuts(tool_args),
+        (13, "timeline") => day_thirteen_timeline(tool_args),
+        (14, "render") => day_fourteen_render(tool_args),
+        (15, "bench") => day_fifteen_bench(tool_args),
+        (19, "animate") => day_nineteen_animate(tool_args),
+        (20, "verify") => day_twenty_verify(tool_args),
+        (20, "export") => day_twenty_export(tool_args),
+        _ => panic!("Day {} has no tool named \"{}\".", day_num, tool),
+    }
+}
+
 // Defining a new binary project for each Advent day was overkill; so we have one main
 // taking an integer on the command line to decide which day to execute. The main panics
 // if the parameter is invalid or non-existent. Once a day is selected we always execute
-// both parts.
+// both parts, unless a tool name follows the day number: in that case only the tool runs.
 fn main() {
     let args: Vec<String> = env::args().collect();
 
-    if args.len() != 2 {
-        println!("Expected 1 command-line argument, got {}", args.len() - 1);
-        panic!("Usage: \"cargo run <day number>\"");
+    if args.len() < 2 {
+        println!("Expected at least 1 command-line argument, got {}", args.len() - 1);
+        panic!("Usage: \"cargo run <day number> [<tool> <tool arguments>...]\"");
     }
 
     let day_num: usize = args[1].parse().expect(
@@ -136,6 +163,11 @@ fn main() {
         );
     }
 
+    if args.len() > 2 {
+        run_tool(day_num, &args[2], &args[3..]);
+        return;
+    }
+
     // Array of function pointers, all of which take no parameter and return nothing.
     let days: Vec<fn() -> ()> = vec![
         day_one_wrapper,
@@ -0,0 +1,139 @@
+// Coordinates on the spiral memory of day 3. Square 1 is at (0, 0), and the spiral proceeds
+// counter-clockwise starting towards the right: square 2 is at (1, 0), square 3 at (1, 1),
+// and so on. The x axis points right and the y axis points up.
+//
+// Ring k of the spiral is made of the squares at distance k from the center, along the
+// largest axis. It ends at the bottom right corner (k, -k) with square (2k + 1)^2, and each of
+// its four edges is 2k squares long.
+
+// Returns the ring a square belongs to.
+fn ring_of(index: u64) -> i64 {
+    ((index - 1).isqrt() as i64 + 1) / 2
+}
+
+// Returns the coordinates of the square with the given index, starting from 1.
+pub fn index_to_coord(index: u64) -> (i64, i64) {
+    assert!(index > 0, "Squares are numbered starting from 1.");
+
+    let ring = ring_of(index);
+    let side = 2 * ring;
+    let last = (2 * ring + 1) * (2 * ring + 1);
+    // How many steps back from the last square of the ring we are.
+    let back = last - index as i64;
+
+    if back < side {
+        (ring - back, -ring)
+    } else if back < 2 * side {
+        (-ring, -ring + (back - side))
+    } else if back < 3 * side {
+        (-ring + (back - 2 * side), ring)
+    } else {
+        (ring, ring - (back - 3 * side))
+    }
+}
+
+// Returns the index of the square at the given coordinates; the inverse of index_to_coord.
+pub fn coord_to_index(x: i64, y: i64) -> u64 {
+    let ring = x.abs().max(y.abs());
+    let side = 2 * ring;
+    let last = (2 * ring + 1) * (2 * ring + 1);
+
+    let back = if y == -ring {
+        ring - x
+    } else if x == -ring {
+        side + (y + ring)
+    } else if y == ring {
+        2 * side + (x + ring)
+    } else {
+        3 * side + (ring - y)
+    };
+
+    (last - back) as u64
+}
+
+// The values written by the stress test: square 1 holds 1, and every other square holds the
+// sum of the values in all the adjacent squares, diagonals included, that come before it.
+// Values are stored by index, so the grid grows as needed and has no fixed size. The values
+// grow quickly, and the iterator ends once one no longer fits in a u64.
+pub struct StressTestValues {
+    values: Vec<u64>,
+}
+
+impl StressTestValues {
+    pub fn new() -> StressTestValues {
+        StressTestValues { values: Vec::new() }
+    }
+}
+
+impl Iterator for StressTestValues {
+    type Item = u64;
+
+    fn next(&mut self) -> Option<u64> {
+        let index = self.values.len() as u64 + 1;
+        let (x, y) = index_to_coord(index);
+        let mut sum: u64 = 0;
+
+        for dx in -1..2 {
+            for dy in -1..2 {
+                let neighbour = coord_to_index(x + dx, y + dy);
+
+                if neighbour < index {
+                    sum = sum.checked_add(self.values[neighbour as usize - 1])?;
+                }
+            }
+        }
+
+        let value = if index == 1 { 1 } else { sum };
+        self.values.push(value);
+        Some(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn coordinates_on_all_edges() {
+        let expected = [
+            (1, (0, 0)),
+            (2, (1, 0)),
+            (3, (1, 1)),
+            (5, (-1, 1)),
+            (7, (-1, -1)),
+            (9, (1, -1)),
+            (10, (2, -1)),
+            (12, (2, 1)),
+            (13, (2, 2)),
+            (15, (0, 2)),
+            (17, (-2, 2)),
+            (19, (-2, 0)),
+            (21, (-2, -2)),
+            (23, (0, -2)),
+            (25, (2, -2)),
+            (26, (3, -2)),
+        ];
+
+        for &(index, coord) in expected.iter() {
+            assert_eq!(index_to_coord(index), coord);
+            assert_eq!(coord_to_index(coord.0, coord.1), index);
+        }
+
+        for index in 1..10_000 {
+            let (x, y) = index_to_coord(index);
+            assert_eq!(coord_to_index(x, y), index);
+        }
+    }
+
+    #[test]
+    fn stress_test_values() {
+        let first: Vec<u64> = StressTestValues::new().take(12).collect();
+        assert_eq!(first, vec![1, 1, 2, 4, 5, 10, 11, 23, 25, 26, 54, 57]);
+
+        // The iterator ends instead of overflowing, and stays ended.
+        let mut values = StressTestValues::new();
+        let count = values.by_ref().count();
+        assert!(count > 400);
+        assert_eq!(values.next(), None);
+    }
+}
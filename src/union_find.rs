@@ -0,0 +1,104 @@
+// Disjoint-set forest over the elements 0..n, with union by size and path compression: any
+// sequence of operations runs in almost linear time.
+pub struct UnionFind {
+    parents: Vec<usize>,
+    sizes: Vec<usize>,
+    num_sets: usize,
+}
+
+impl UnionFind {
+    pub fn new() -> UnionFind {
+        UnionFind {
+            parents: Vec::new(),
+            sizes: Vec::new(),
+            num_sets: 0,
+        }
+    }
+
+    // Adds a new element in a set of its own, and returns it.
+    pub fn make_set(&mut self) -> usize {
+        let id = self.parents.len();
+        self.parents.push(id);
+        self.sizes.push(1);
+        self.num_sets += 1;
+        id
+    }
+
+    // Number of elements.
+    pub fn len(&self) -> usize {
+        self.parents.len()
+    }
+
+    pub fn num_sets(&self) -> usize {
+        self.num_sets
+    }
+
+    // Finds the representative of the set containing |x|, compressing the path on the way.
+    pub fn find(&mut self, x: usize) -> usize {
+        let mut root = x;
+        while self.parents[root] != root {
+            root = self.parents[root];
+        }
+
+        let mut current = x;
+        while self.parents[current] != root {
+            let next = self.parents[current];
+            self.parents[current] = root;
+            current = next;
+        }
+
+        root
+    }
+
+    // Merges the sets containing |a| and |b|. The smaller set goes under the larger one, so
+    // that trees stay shallow.
+    pub fn union(&mut self, a: usize, b: usize) {
+        let mut root_a = self.find(a);
+        let mut root_b = self.find(b);
+
+        if root_a == root_b {
+            return;
+        }
+
+        if self.sizes[root_a] < self.sizes[root_b] {
+            ::std::mem::swap(&mut root_a, &mut root_b);
+        }
+
+        self.parents[root_b] = root_a;
+        self.sizes[root_a] += self.sizes[root_b];
+        self.num_sets -= 1;
+    }
+
+    // Number of elements in the set containing |x|.
+    pub fn size(&mut self, x: usize) -> usize {
+        let root = self.find(x);
+        self.sizes[root]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unions() {
+        let mut sets = UnionFind::new();
+        for i in 0..6 {
+            assert_eq!(sets.make_set(), i);
+        }
+        assert_eq!((sets.len(), sets.num_sets()), (6, 6));
+
+        sets.union(0, 1);
+        sets.union(2, 3);
+        sets.union(1, 3);
+        // Merging elements already in the same set changes nothing.
+        sets.union(0, 2);
+
+        assert_eq!((sets.len(), sets.num_sets()), (6, 3));
+        assert_eq!(sets.find(0), sets.find(3));
+        assert_ne!(sets.find(0), sets.find(4));
+        assert_ne!(sets.find(4), sets.find(5));
+        assert_eq!(sets.size(2), 4);
+        assert_eq!(sets.size(5), 1);
+    }
+}
@@ -1,5 +1,6 @@
 use std::io::prelude::*;
 use std::fs::File;
+use std::time::Instant;
 
 pub fn read_input(path: &str) -> String {
     let mut f = File::open(path).expect("File not found.");
@@ -9,3 +10,58 @@ pub fn read_input(path: &str) -> String {
     );
     contents
 }
+
+pub fn write_output(path: &str, contents: &str) {
+    let mut f = File::create(path).expect("Could not create the output file.");
+    f.write_all(contents.as_bytes()).expect(
+        "Error writing the output file.",
+    );
+}
+
+// Runs |f| and prints how long it took, after |label| and the description of the result
+// given by |describe|. Used by the benchmark tools.
+pub fn print_timed<T, F, D>(label: &str, f: F, describe: D) -> T
+where
+    F: FnOnce() -> T,
+    D: FnOnce(&T) -> String,
+{
+    let start = Instant::now();
+    let result = f();
+    let elapsed = start.elapsed();
+
+    println!(
+        "{}: {} in {}.{:03} seconds.",
+        label,
+        describe(&result),
+        elapsed.as_secs(),
+        elapsed.subsec_millis()
+    );
+
+    result
+}
+
+// Picks a colour for the n-th item of a set, e.g. a region of a grid, as RGB. Stepping the
+// hue by the golden ratio keeps the colours of items with close indices far apart.
+pub fn distinct_colour(index: u32) -> (u8, u8, u8) {
+    let hue = (f64::from(index) * 0.618_033_988_75).fract() * 6.0;
+    let saturation = 0.65;
+    let value = 0.95;
+
+    let chroma = value * saturation;
+    let x = chroma * (1.0 - (hue % 2.0 - 1.0).abs());
+    let (r, g, b) = match hue as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    let m = value - chroma;
+
+    (
+        ((r + m) * 255.0) as u8,
+        ((g + m) * 255.0) as u8,
+        ((b + m) * 255.0) as u8,
+    )
+}